@@ -0,0 +1,51 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Compare the time it takes to lex a big input
+//! with the rules tried one by one
+//! and with the rules compiled into a single automaton per state.
+//!
+//! Run with: `cargo bench --bench lexer`.
+
+include!("../tests/nix/lexer.rs");
+
+use std::time::Duration;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let input = std::fs::read_to_string("tests/nix/cases/lib/input")
+        .unwrap()
+        .repeat(20);
+
+    let lexer_rules = lexer_rules();
+    let compiled = lexer_rules.compile();
+
+    assert_eq!(
+        santiago::lexer::lex(&lexer_rules, &input).unwrap(),
+        santiago::lexer::lex(&compiled, &input).unwrap(),
+    );
+
+    let interpreted = bench(&lexer_rules, &input);
+    let compiled = bench(&compiled, &input);
+
+    println!("Input: {} bytes, {ITERATIONS} iterations", input.len());
+    println!("  interpreted: {interpreted:?} per iteration");
+    println!("  compiled:    {compiled:?} per iteration");
+    println!(
+        "  speedup:     {:.2}x",
+        interpreted.as_secs_f64() / compiled.as_secs_f64()
+    );
+}
+
+fn bench(lexer_rules: &LexerRules, input: &str) -> Duration {
+    let start = Instant::now();
+
+    for _ in 0..ITERATIONS {
+        std::hint::black_box(santiago::lexer::lex(lexer_rules, input).unwrap());
+    }
+
+    start.elapsed() / ITERATIONS
+}
@@ -0,0 +1,185 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::LexerError;
+use crate::parser::LexAndParseError;
+use crate::parser::ParseError;
+use std::ops::Range;
+
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+/// Report of an error that points to the relevant part of the input.
+///
+/// Normally you create a [Diagnostic]
+/// from a [LexerError] or a [ParseError] with [From].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// Short description of the error.
+    pub message: String,
+    /// Range of bytes of the input where the error was found.
+    pub span:    Range<usize>,
+    /// Description of the `span`, shown next to its underline.
+    pub label:   String,
+    /// Additional information, shown after the input.
+    pub notes:   Vec<String>,
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(error: &LexerError) -> Diagnostic {
+        let match_len = error.match_len.unwrap_or(0);
+
+        Diagnostic {
+            message: error.message.clone(),
+            span:    error.byte_index..error.byte_index + match_len,
+            label:   match error.match_len {
+                Some(_) => String::new(),
+                None => "no lexer rule matches this input".to_string(),
+            },
+            notes:   vec![format!(
+                "With states stack: {:?}",
+                error.states_stack
+            )],
+        }
+    }
+}
+
+impl<AST> From<&ParseError<AST>> for Diagnostic {
+    fn from(error: &ParseError<AST>) -> Diagnostic {
+        let mut notes = Vec::new();
+
+        if !error.expected_lexemes.is_empty() {
+            notes.push(format!(
+                "Expected one of: {}",
+                error
+                    .expected_lexemes
+                    .iter()
+                    .map(|kind| format!("{kind:?}"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+
+        if error.expected_lexemes.is_empty() && error.expected_rules.is_empty()
+        {
+            // Not a failure to match the next lexeme,
+            // so keep the explanation given by the parser.
+            let mut lines = error.message.lines();
+
+            return Diagnostic {
+                message: lines.next().unwrap_or_default().to_string(),
+                span:    error
+                    .found
+                    .as_ref()
+                    .map(|found| found.byte_range())
+                    .unwrap_or(0..0),
+                label:   String::new(),
+                notes:   lines.map(str::to_string).collect(),
+            };
+        }
+
+        match &error.found {
+            Some(found) => Diagnostic {
+                message: "Unexpected lexeme".to_string(),
+                span: found.byte_range(),
+                label: format!("found {:?}", found.kind),
+                notes,
+            },
+            None => {
+                let end = error
+                    .at
+                    .as_ref()
+                    .map(|at| at.position_end.byte_index)
+                    .unwrap_or(0);
+
+                Diagnostic {
+                    message: "Unexpected end of input".to_string(),
+                    span: end..end,
+                    label: "the input ends here".to_string(),
+                    notes,
+                }
+            }
+        }
+    }
+}
+
+impl<AST> From<&LexAndParseError<AST>> for Diagnostic {
+    fn from(error: &LexAndParseError<AST>) -> Diagnostic {
+        match error {
+            LexAndParseError::Lexer(error) => Diagnostic::from(error),
+            LexAndParseError::Parser(error) => Diagnostic::from(error),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// Render this [Diagnostic] in the style of the Rust compiler,
+    /// showing the line of `input` where the error was found.
+    ///
+    /// `input` must be the same input that produced the error,
+    /// and `file_name` is only used to tell the user where the input is.
+    /// If `colors` is true, the report uses ANSI escape codes.
+    pub fn render(&self, file_name: &str, input: &str, colors: bool) -> String {
+        let paint = |color: &str, text: &str| -> String {
+            if colors {
+                format!("{color}{text}{RESET}")
+            } else {
+                text.to_string()
+            }
+        };
+
+        let start = self.span.start.min(input.len());
+        let line_start =
+            input[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = input[start..]
+            .find('\n')
+            .map_or(input.len(), |index| start + index);
+        let line = &input[line_start..line_end];
+        let line_number = input[..start].matches('\n').count() + 1;
+        let column = input[line_start..start].chars().count() + 1;
+
+        // The underline does not go beyond the line where the span starts.
+        let end = self.span.end.clamp(start, line_end);
+        let underline = "^".repeat(input[start..end].chars().count().max(1));
+
+        let gutter = " ".repeat(line_number.to_string().len());
+        let bar = paint(BLUE, "|");
+
+        let mut report = String::new();
+        report.push_str(&format!(
+            "{}{}\n",
+            paint(RED, "error"),
+            paint(BOLD, &format!(": {}", self.message)),
+        ));
+        report.push_str(&format!(
+            "{gutter}{} {file_name}:{line_number}:{column}\n",
+            paint(BLUE, "-->"),
+        ));
+        report.push_str(&format!("{gutter} {bar}\n"));
+        report.push_str(&format!(
+            "{} {bar} {line}\n",
+            paint(BLUE, &line_number.to_string()),
+        ));
+        report.push_str(&format!(
+            "{gutter} {bar} {}{}",
+            " ".repeat(column - 1),
+            paint(RED, &underline),
+        ));
+        if !self.label.is_empty() {
+            report.push_str(&format!(" {}", paint(RED, &self.label)));
+        }
+        report.push('\n');
+
+        for note in &self.notes {
+            report.push_str(&format!(
+                "{gutter} {} note: {note}\n",
+                paint(BLUE, "=")
+            ));
+        }
+
+        report
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Render lexer and parser errors as human readable reports
+//! that point to the relevant part of the input.
+//!
+//! Please read the [crate documentation](crate) for more information and examples.
+
+mod diagnostic;
+
+pub use diagnostic::Diagnostic;
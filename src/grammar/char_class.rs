@@ -0,0 +1,99 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+/// Set of characters,
+/// written like a bracket expression of a regular expression,
+/// for instance `[a-z_]` or `[^\n]`.
+///
+/// Normally you create a [CharClass] by using
+/// [GrammarBuilder::rule_to_chars()](crate::grammar::GrammarBuilder::rule_to_chars()).
+#[derive(Clone, Debug)]
+pub(crate) struct CharClass {
+    negated: bool,
+    /// Inclusive ranges of characters.
+    ranges:  Vec<(char, char)>,
+}
+
+impl CharClass {
+    /// Parse a bracket expression like `[a-z_]`.
+    ///
+    /// Supports ranges, negation with `^`,
+    /// and the escapes `\n`, `\r`, `\t`, and `\` followed by any other character.
+    pub(crate) fn new(class: &str) -> Result<CharClass, String> {
+        let inner = match class
+            .strip_prefix('[')
+            .and_then(|class| class.strip_suffix(']'))
+        {
+            Some(inner) => inner,
+            None => {
+                return Err("It must start with [ and end with ]".to_string());
+            }
+        };
+        let (negated, inner) = match inner.strip_prefix('^') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+
+        let mut chars = Vec::new();
+        let mut iter = inner.chars();
+        while let Some(char) = iter.next() {
+            chars.push(match char {
+                '\\' => match iter.next() {
+                    Some('n') => ('\n', true),
+                    Some('r') => ('\r', true),
+                    Some('t') => ('\t', true),
+                    Some(char) => (char, true),
+                    None => return Err("It ends with an escape".to_string()),
+                },
+                char => (char, false),
+            });
+        }
+
+        let mut ranges = Vec::new();
+        let mut index = 0;
+        while index < chars.len() {
+            let (start, _) = chars[index];
+
+            match chars.get(index + 1) {
+                Some(('-', false)) if index + 2 < chars.len() => {
+                    let (end, _) = chars[index + 2];
+                    if start > end {
+                        return Err(format!(
+                            "The range {start:?}-{end:?} is out of order"
+                        ));
+                    }
+
+                    ranges.push((start, end));
+                    index += 3;
+                }
+                _ => {
+                    ranges.push((start, start));
+                    index += 1;
+                }
+            }
+        }
+
+        if ranges.is_empty() {
+            return Err("It does not contain any character".to_string());
+        }
+
+        Ok(CharClass { negated, ranges })
+    }
+
+    /// Whether `kind` is a single character that belongs to this [CharClass].
+    pub(crate) fn matches(&self, kind: &str) -> bool {
+        let mut chars = kind.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(char), None) => {
+                self.negated
+                    != self
+                        .ranges
+                        .iter()
+                        .any(|(start, end)| *start <= char && char <= *end)
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,359 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::grammar::Grammar;
+use crate::grammar::ProductionKind;
+use crate::grammar::START_RULE_NAME;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Static analysis of a [Grammar],
+/// useful for finding mistakes in big grammars.
+///
+/// Normally you create a [GrammarAnalysis]
+/// by using [Grammar::analyze()].
+pub struct GrammarAnalysis {
+    /// Names of the rules that cannot be reached from the start rule.
+    pub unreachable_rules:  HashSet<Rc<String>>,
+    /// Names of the rules that can never derive
+    /// a finite sequence of [Lexemes](crate::lexer::Lexeme).
+    pub unproductive_rules: HashSet<Rc<String>>,
+    /// Names of the rules that can derive
+    /// the empty sequence of [Lexemes](crate::lexer::Lexeme).
+    pub nullable_rules:     HashSet<Rc<String>>,
+    /// Names of the rules that can derive themselves: `A =>+ A`.
+    pub cyclic_rules:       HashSet<Rc<String>>,
+    /// Kinds of the [Lexemes](crate::lexer::Lexeme)
+    /// that can begin a derivation of each rule.
+    pub first:              HashMap<Rc<String>, HashSet<String>>,
+    /// Kinds of the [Lexemes](crate::lexer::Lexeme)
+    /// that can immediately follow a derivation of each rule.
+    pub follow:             HashMap<Rc<String>, HashSet<String>>,
+    /// Names of the rules whose derivation can be followed
+    /// by the end of the input.
+    pub followed_by_end:    HashSet<Rc<String>>,
+}
+
+impl GrammarAnalysis {
+    pub(crate) fn new<AST>(grammar: &Grammar<AST>) -> GrammarAnalysis {
+        let nullable_rules = compute_nullable_rules(grammar);
+        let first = compute_first(grammar, &nullable_rules);
+        let (follow, followed_by_end) =
+            compute_follow(grammar, &nullable_rules, &first);
+
+        GrammarAnalysis {
+            unreachable_rules: compute_unreachable_rules(grammar),
+            unproductive_rules: compute_unproductive_rules(grammar),
+            cyclic_rules: compute_cyclic_rules(grammar, &nullable_rules),
+            nullable_rules,
+            first,
+            follow,
+            followed_by_end,
+        }
+    }
+
+    /// Kinds of the [Lexemes](crate::lexer::Lexeme)
+    /// that can begin a derivation of the given sequence of rules.
+    pub fn first_of_rules(&self, rule_names: &[String]) -> HashSet<String> {
+        let mut first = HashSet::new();
+
+        for rule_name in rule_names {
+            if let Some(rule_first) = self.first.get(rule_name) {
+                first.extend(rule_first.iter().cloned());
+            }
+
+            if !self.nullable_rules.contains(rule_name) {
+                break;
+            }
+        }
+
+        first
+    }
+}
+
+fn compute_unreachable_rules<AST>(
+    grammar: &Grammar<AST>,
+) -> HashSet<Rc<String>> {
+    let mut reachable: HashSet<&String> = HashSet::new();
+
+    let mut pending: Vec<&String> = grammar
+        .rules
+        .keys()
+        .filter(|rule_name| ***rule_name == START_RULE_NAME)
+        .map(|rule_name| &**rule_name)
+        .collect();
+
+    while let Some(rule_name) = pending.pop() {
+        if !reachable.insert(rule_name) {
+            continue;
+        }
+
+        for production in &grammar.rules[rule_name].productions {
+            if let ProductionKind::Rules = production.kind {
+                for symbol in &production.symbols {
+                    if grammar.rules.contains_key(symbol) {
+                        pending.push(symbol);
+                    }
+                }
+            }
+        }
+    }
+
+    grammar
+        .rules
+        .keys()
+        .filter(|rule_name| !reachable.contains(&***rule_name))
+        .cloned()
+        .collect()
+}
+
+fn compute_unproductive_rules<AST>(
+    grammar: &Grammar<AST>,
+) -> HashSet<Rc<String>> {
+    let mut productive: HashSet<&String> = HashSet::new();
+
+    loop {
+        let mut converged = true;
+
+        for (rule_name, rule) in &grammar.rules {
+            if productive.contains(&**rule_name) {
+                continue;
+            }
+
+            if rule.productions.iter().any(|production| match production.kind {
+                ProductionKind::Lexemes => true,
+                ProductionKind::Rules => production
+                    .symbols
+                    .iter()
+                    .all(|symbol| productive.contains(symbol)),
+            }) {
+                productive.insert(rule_name);
+                converged = false;
+            }
+        }
+
+        if converged {
+            break;
+        }
+    }
+
+    grammar
+        .rules
+        .keys()
+        .filter(|rule_name| !productive.contains(&***rule_name))
+        .cloned()
+        .collect()
+}
+
+fn compute_nullable_rules<AST>(grammar: &Grammar<AST>) -> HashSet<Rc<String>> {
+    let mut nullable: HashSet<Rc<String>> = HashSet::new();
+
+    loop {
+        let mut converged = true;
+
+        for (rule_name, rule) in &grammar.rules {
+            if nullable.contains(rule_name) {
+                continue;
+            }
+
+            if rule.productions.iter().any(|production| match production.kind {
+                ProductionKind::Lexemes => production.symbols.is_empty(),
+                ProductionKind::Rules => production
+                    .symbols
+                    .iter()
+                    .all(|symbol| nullable.contains(symbol)),
+            }) {
+                nullable.insert(rule_name.clone());
+                converged = false;
+            }
+        }
+
+        if converged {
+            break;
+        }
+    }
+
+    nullable
+}
+
+fn compute_cyclic_rules<AST>(
+    grammar: &Grammar<AST>,
+    nullable_rules: &HashSet<Rc<String>>,
+) -> HashSet<Rc<String>> {
+    // `A -> B` if `A := α B β` where both `α` and `β` are nullable.
+    let mut unit_derivations: HashMap<&String, HashSet<&String>> =
+        HashMap::new();
+
+    for (rule_name, rule) in &grammar.rules {
+        let targets = unit_derivations.entry(rule_name).or_default();
+
+        for production in &rule.productions {
+            if let ProductionKind::Rules = production.kind {
+                for (index, symbol) in production.symbols.iter().enumerate() {
+                    let others_nullable = production
+                        .symbols
+                        .iter()
+                        .enumerate()
+                        .filter(|(other_index, _)| *other_index != index)
+                        .all(|(_, other)| nullable_rules.contains(other));
+
+                    if others_nullable && grammar.rules.contains_key(symbol) {
+                        targets.insert(symbol);
+                    }
+                }
+            }
+        }
+    }
+
+    grammar
+        .rules
+        .keys()
+        .filter(|rule_name| {
+            let mut visited: HashSet<&String> = HashSet::new();
+            let mut pending: Vec<&String> =
+                unit_derivations[&***rule_name].iter().copied().collect();
+
+            while let Some(target) = pending.pop() {
+                if target == &***rule_name {
+                    return true;
+                }
+
+                if visited.insert(target) {
+                    pending.extend(unit_derivations[target].iter().copied());
+                }
+            }
+
+            false
+        })
+        .cloned()
+        .collect()
+}
+
+fn compute_first<AST>(
+    grammar: &Grammar<AST>,
+    nullable_rules: &HashSet<Rc<String>>,
+) -> HashMap<Rc<String>, HashSet<String>> {
+    let mut first: HashMap<Rc<String>, HashSet<String>> = grammar
+        .rules
+        .keys()
+        .map(|rule_name| (rule_name.clone(), HashSet::new()))
+        .collect();
+
+    loop {
+        let mut converged = true;
+
+        for (rule_name, rule) in &grammar.rules {
+            let mut rule_first = HashSet::new();
+
+            for production in &rule.productions {
+                match production.kind {
+                    ProductionKind::Lexemes => {
+                        if let Some(symbol) = production.symbols.first() {
+                            rule_first.insert(symbol.clone());
+                        }
+                    }
+                    ProductionKind::Rules => {
+                        for symbol in &production.symbols {
+                            if let Some(symbol_first) = first.get(symbol) {
+                                rule_first.extend(symbol_first.iter().cloned());
+                            }
+
+                            if !nullable_rules.contains(symbol) {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let current = first.get_mut(rule_name).unwrap();
+            if rule_first.len() > current.len() {
+                *current = rule_first;
+                converged = false;
+            }
+        }
+
+        if converged {
+            break;
+        }
+    }
+
+    first
+}
+
+#[allow(clippy::type_complexity)]
+fn compute_follow<AST>(
+    grammar: &Grammar<AST>,
+    nullable_rules: &HashSet<Rc<String>>,
+    first: &HashMap<Rc<String>, HashSet<String>>,
+) -> (HashMap<Rc<String>, HashSet<String>>, HashSet<Rc<String>>) {
+    let mut follow: HashMap<Rc<String>, HashSet<String>> = grammar
+        .rules
+        .keys()
+        .map(|rule_name| (rule_name.clone(), HashSet::new()))
+        .collect();
+    let mut followed_by_end: HashSet<Rc<String>> = grammar
+        .rules
+        .keys()
+        .filter(|rule_name| ***rule_name == START_RULE_NAME)
+        .cloned()
+        .collect();
+
+    loop {
+        let mut converged = true;
+
+        for (rule_name, rule) in &grammar.rules {
+            for production in &rule.productions {
+                if let ProductionKind::Lexemes = production.kind {
+                    continue;
+                }
+
+                for (index, symbol) in production.symbols.iter().enumerate() {
+                    let symbol = match grammar.rules.get_key_value(symbol) {
+                        Some((symbol, _)) => symbol,
+                        None => continue,
+                    };
+
+                    let mut symbol_follow = HashSet::new();
+                    let mut rest_nullable = true;
+
+                    for next_symbol in &production.symbols[index + 1..] {
+                        if let Some(next_first) = first.get(next_symbol) {
+                            symbol_follow.extend(next_first.iter().cloned());
+                        }
+
+                        if !nullable_rules.contains(next_symbol) {
+                            rest_nullable = false;
+                            break;
+                        }
+                    }
+
+                    if rest_nullable {
+                        symbol_follow.extend(follow[rule_name].iter().cloned());
+
+                        if followed_by_end.contains(rule_name)
+                            && followed_by_end.insert(symbol.clone())
+                        {
+                            converged = false;
+                        }
+                    }
+
+                    let current = follow.get_mut(symbol).unwrap();
+                    for kind in symbol_follow {
+                        if current.insert(kind) {
+                            converged = false;
+                        }
+                    }
+                }
+            }
+        }
+
+        if converged {
+            break;
+        }
+    }
+
+    (follow, followed_by_end)
+}
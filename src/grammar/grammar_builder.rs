@@ -3,8 +3,10 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::grammar::Associativity;
+use crate::grammar::CharClass;
 use crate::grammar::Disambiguation;
 use crate::grammar::Grammar;
+use crate::grammar::GrammarError;
 use crate::grammar::GrammarRule;
 use crate::grammar::Production;
 use crate::grammar::ProductionAction;
@@ -20,8 +22,13 @@ use std::rc::Rc;
 ///
 /// Please read the [crate documentation](crate) for more information and examples.
 pub struct GrammarBuilder<AST> {
-    current_precedence: usize,
-    grammar:            Grammar<AST>,
+    char_classes:           HashMap<String, Rc<CharClass>>,
+    current_precedence:     usize,
+    errors:                 Vec<GrammarError<AST>>,
+    grammar:                Grammar<AST>,
+    last_production:        Option<(Rc<String>, Rc<Production<AST>>)>,
+    precedences:            HashMap<String, Disambiguation>,
+    production_precedences: Vec<(Rc<String>, Rc<Production<AST>>, String)>,
 }
 
 impl<AST> Default for GrammarBuilder<AST> {
@@ -34,8 +41,13 @@ impl<AST> GrammarBuilder<AST> {
     /// Creates a new [GrammarBuilder] with no rules.
     pub fn new() -> GrammarBuilder<AST> {
         GrammarBuilder {
-            current_precedence: 0,
-            grammar:            Grammar { rules: HashMap::new() },
+            char_classes:           HashMap::new(),
+            current_precedence:     0,
+            errors:                 Vec::new(),
+            grammar:                Grammar { rules: HashMap::new() },
+            last_production:        None,
+            precedences:            HashMap::new(),
+            production_precedences: Vec::new(),
         }
     }
 
@@ -59,16 +71,25 @@ impl<AST> GrammarBuilder<AST> {
             );
         }
 
+        let char_class = match (&symbols_kind, symbols) {
+            (ProductionKind::Lexemes, [symbol]) => {
+                self.char_classes.get(*symbol).cloned()
+            }
+            _ => None,
+        };
+
         let production = Rc::new(Production {
-            action:         Rc::new(production_action),
+            action: Rc::new(production_action),
+            char_class,
+            target_char_classes: RefCell::new(Vec::new()),
             target_lexemes: RefCell::new(HashSet::new()),
-            symbols:        symbols
-                .iter()
-                .map(|symbol| symbol.to_string())
-                .collect(),
-            kind:           symbols_kind,
+            disambiguation: RefCell::new(None),
+            symbols: symbols.iter().map(|symbol| symbol.to_string()).collect(),
+            kind: symbols_kind,
         });
 
+        self.last_production = Some((rule_name.clone(), production.clone()));
+
         match self.grammar.rules.get_mut(&rule_name) {
             Some(rule) => {
                 rule.productions.push(production);
@@ -77,9 +98,11 @@ impl<AST> GrammarBuilder<AST> {
                 self.grammar.rules.insert(
                     rule_name.clone(),
                     GrammarRule {
-                        name:           rule_name.clone(),
-                        disambiguation: None,
-                        productions:    vec![production],
+                        name:            rule_name.clone(),
+                        disambiguation:  None,
+                        productions:     vec![production],
+                        not_followed_by: None,
+                        rejects:         HashSet::new(),
                     },
                 );
             }
@@ -87,14 +110,14 @@ impl<AST> GrammarBuilder<AST> {
     }
 
     /// Map a rule with name `name` to zero or more lexemes.
-    pub fn rule_to_lexemes<Action: 'static>(
+    pub fn rule_to_lexemes<Action>(
         &mut self,
         rule_name: &str,
         lexeme_kinds: &[&str],
         action: Action,
     ) -> &mut GrammarBuilder<AST>
     where
-        Action: Fn(&[&Rc<Lexeme>]) -> AST,
+        Action: Fn(&[&Rc<Lexeme>]) -> AST + 'static,
     {
         self.rule_to_symbols(
             rule_name,
@@ -106,15 +129,130 @@ impl<AST> GrammarBuilder<AST> {
         self
     }
 
+    /// Map a rule with name `name` to a single character
+    /// that belongs to the character class `class`,
+    /// written like a bracket expression of a regular expression,
+    /// for instance `[a-z_]` or `[^\n]`.
+    ///
+    /// This is meant for scannerless grammars,
+    /// whose input is parsed with [parse_str()](crate::parser::parse_str()).
+    pub fn rule_to_chars<Action>(
+        &mut self,
+        rule_name: &str,
+        class: &str,
+        action: Action,
+    ) -> &mut GrammarBuilder<AST>
+    where
+        Action: Fn(&[&Rc<Lexeme>]) -> AST + 'static,
+    {
+        match CharClass::new(class) {
+            Ok(char_class) => {
+                self.char_classes
+                    .insert(class.to_string(), Rc::new(char_class));
+            }
+            Err(message) => {
+                self.errors.push(GrammarError::InvalidCharClass {
+                    rule_name: rule_name.to_string(),
+                    class: class.to_string(),
+                    message,
+                });
+            }
+        }
+
+        self.rule_to_lexemes(rule_name, &[class], action)
+    }
+
+    /// Map a rule with name `name` to the characters of `string`.
+    ///
+    /// This is meant for scannerless grammars,
+    /// whose input is parsed with [parse_str()](crate::parser::parse_str()).
+    pub fn rule_to_string<Action>(
+        &mut self,
+        rule_name: &str,
+        string: &str,
+        action: Action,
+    ) -> &mut GrammarBuilder<AST>
+    where
+        Action: Fn(&[&Rc<Lexeme>]) -> AST + 'static,
+    {
+        let chars: Vec<String> = string.chars().map(String::from).collect();
+        let chars: Vec<&str> = chars.iter().map(String::as_str).collect();
+
+        self.rule_to_lexemes(rule_name, &chars, action)
+    }
+
+    /// Forbid the rule with name `rule_name`
+    /// from being followed by a character of the character class `class`,
+    /// see [GrammarBuilder::rule_to_chars()].
+    ///
+    /// Together with [GrammarBuilder::reject()]
+    /// this implements the longest match of identifiers and keywords
+    /// in scannerless grammars.
+    pub fn not_followed_by(
+        &mut self,
+        rule_name: &str,
+        class: &str,
+    ) -> &mut GrammarBuilder<AST> {
+        let char_class = match CharClass::new(class) {
+            Ok(char_class) => Rc::new(char_class),
+            Err(message) => {
+                self.errors.push(GrammarError::InvalidCharClass {
+                    rule_name: rule_name.to_string(),
+                    class: class.to_string(),
+                    message,
+                });
+                return self;
+            }
+        };
+
+        match self.grammar.rules.get_mut(&Rc::new(rule_name.to_string())) {
+            Some(rule) => {
+                rule.not_followed_by = Some(char_class);
+            }
+            None => {
+                self.errors.push(GrammarError::UndefinedRestriction {
+                    rule_name: rule_name.to_string(),
+                });
+            }
+        }
+
+        self
+    }
+
+    /// Forbid the rule with name `rule_name` from matching any of `strings`,
+    /// comparing them with the kinds of the [Lexeme]s it matches,
+    /// which in scannerless grammars are the characters of the input.
+    ///
+    /// For instance, to stop an identifier from matching a keyword.
+    pub fn reject(
+        &mut self,
+        rule_name: &str,
+        strings: &[&str],
+    ) -> &mut GrammarBuilder<AST> {
+        match self.grammar.rules.get_mut(&Rc::new(rule_name.to_string())) {
+            Some(rule) => {
+                rule.rejects
+                    .extend(strings.iter().map(|string| string.to_string()));
+            }
+            None => {
+                self.errors.push(GrammarError::UndefinedRestriction {
+                    rule_name: rule_name.to_string(),
+                });
+            }
+        }
+
+        self
+    }
+
     /// Map a rule with name `name` to zero or more rules.
-    pub fn rule_to_rules<Action: 'static>(
+    pub fn rule_to_rules<Action>(
         &mut self,
         rule_name: &str,
         rule_names: &[&str],
         action: Action,
     ) -> &mut GrammarBuilder<AST>
     where
-        Action: Fn(Vec<AST>) -> AST,
+        Action: Fn(Vec<AST>) -> AST + 'static,
     {
         self.rule_to_symbols(
             rule_name,
@@ -126,10 +264,44 @@ impl<AST> GrammarBuilder<AST> {
         self
     }
 
+    /// Override the precedence and [Associativity]
+    /// of the last added production,
+    /// which must be of kind [ProductionKind::Rules],
+    /// with the ones given to `precedence` by [GrammarBuilder::disambiguate()].
+    ///
+    /// `precedence` can be the name of a rule,
+    /// or a name that is only used for disambiguation,
+    /// for instance to make an unary minus bind tighter than a binary minus.
+    pub fn precedence(&mut self, precedence: &str) -> &mut GrammarBuilder<AST> {
+        match &self.last_production {
+            Some((rule_name, production))
+                if matches!(production.kind, ProductionKind::Rules) =>
+            {
+                self.production_precedences.push((
+                    rule_name.clone(),
+                    production.clone(),
+                    precedence.to_string(),
+                ));
+            }
+            _ => {
+                self.errors.push(GrammarError::PrecedenceWithoutProduction {
+                    precedence: precedence.to_string(),
+                });
+            }
+        }
+
+        self
+    }
+
     /// Create a disambiguation
     /// with the specified `associativity`,
     /// granting the rules with names `rule_names` equal precedence.
     ///
+    /// `rule_names` may also contain the names given to
+    /// [GrammarBuilder::precedence()], before or after it is called,
+    /// in which case the productions that use them
+    /// are granted this precedence.
+    ///
     /// Increases the precedence counter for future invocations.
     pub fn disambiguate(
         &mut self,
@@ -138,20 +310,19 @@ impl<AST> GrammarBuilder<AST> {
     ) -> &mut GrammarBuilder<AST> {
         for rule_name in rule_names {
             let rule_name = rule_name.to_string();
+            let disambiguation = Disambiguation {
+                associativity: associativity.clone(),
+                precedence:    self.current_precedence,
+            };
 
             match self.grammar.rules.get_mut(&rule_name) {
                 Some(rule) => {
-                    rule.disambiguation = Some(Disambiguation {
-                        associativity: associativity.clone(),
-                        precedence:    self.current_precedence,
-                    })
+                    rule.disambiguation = Some(disambiguation.clone());
                 }
                 None => {
-                    panic!(
-                        "\n\nError while trying to disambiguate a rule with \
-                         name: {rule_name}\nWhich has not been previously \
-                         defined.\n\n"
-                    );
+                    // Either the name of a precedence,
+                    // or an error reported by try_finish.
+                    self.precedences.insert(rule_name, disambiguation);
                 }
             }
         }
@@ -232,28 +403,155 @@ impl<AST> GrammarBuilder<AST> {
     }
 
     /// Return the created [Grammar], performing a few validations first.
+    ///
+    /// Panics if the [Grammar] is not valid,
+    /// see [GrammarBuilder::try_finish()] for a fallible alternative.
+    ///
+    /// As in previous versions,
+    /// an empty [Grammar] and duplicated productions are accepted here,
+    /// and only reported by [GrammarBuilder::try_finish()].
     pub fn finish(&mut self) -> Grammar<AST> {
-        for (rule_name, rule) in self.grammar.rules.iter() {
+        let errors: Vec<String> = self
+            .validate()
+            .iter()
+            .filter(|error| {
+                !matches!(
+                    error,
+                    GrammarError::Empty
+                        | GrammarError::DuplicateProduction { .. }
+                )
+            })
+            .map(GrammarError::to_string)
+            .collect();
+
+        if !errors.is_empty() {
+            panic!("\n\n{}\n\n", errors.join("\n\n"));
+        }
+
+        self.grammar.clone()
+    }
+
+    /// Return the created [Grammar], performing a few validations first.
+    ///
+    /// Returns all the [GrammarError]s found if the [Grammar] is not valid.
+    pub fn try_finish(
+        &mut self,
+    ) -> Result<Grammar<AST>, Vec<GrammarError<AST>>> {
+        let errors = self.validate();
+
+        if errors.is_empty() {
+            Ok(self.grammar.clone())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Validate the [Grammar] and resolve the precedences of its productions,
+    /// returning all the [GrammarError]s found.
+    ///
+    /// The [Grammar] is only prepared for parsing
+    /// if its rules and precedences are all defined.
+    fn validate(&mut self) -> Vec<GrammarError<AST>> {
+        if self.grammar.rules.is_empty() {
+            return vec![GrammarError::Empty];
+        }
+
+        let mut errors = self.errors.clone();
+
+        let mut precedences: Vec<&String> = self.precedences.keys().collect();
+        precedences.sort();
+
+        for precedence in precedences {
+            if !self
+                .production_precedences
+                .iter()
+                .any(|(_, _, used_precedence)| used_precedence == precedence)
+            {
+                errors.push(GrammarError::UndefinedDisambiguation {
+                    rule_name: precedence.clone(),
+                });
+            }
+        }
+
+        let mut undefined_rules = false;
+        let mut rule_names: Vec<&Rc<String>> =
+            self.grammar.rules.keys().collect();
+        rule_names.sort();
+
+        for rule_name in rule_names {
+            let rule = &self.grammar.rules[rule_name];
+            let mut productions = HashSet::new();
+
             for production in &rule.productions {
+                let key = (&production.kind, &production.symbols);
+
+                if !productions.insert(key) {
+                    errors.push(GrammarError::DuplicateProduction {
+                        rule_name:  rule_name.clone(),
+                        production: production.clone(),
+                    });
+                }
+
                 if let ProductionKind::Rules = production.kind {
                     for symbol in &production.symbols {
                         if !self.grammar.rules.contains_key(symbol) {
-                            panic!(
-                                "\n\nError at rule: {rule_name}\nIn \
-                                 production: {production}\nYour grammar \
-                                 references a rule with name: {symbol}\nBut \
-                                 this rule has not been defined in the \
-                                 grammar.\n\n",
-                            )
+                            undefined_rules = true;
+                            errors.push(GrammarError::UndefinedRule {
+                                rule_name:  rule_name.clone(),
+                                production: production.clone(),
+                                symbol:     symbol.clone(),
+                            });
                         }
                     }
                 }
             }
         }
 
+        let mut undefined_precedences = false;
+        for (rule_name, production, precedence) in &self.production_precedences
+        {
+            let disambiguation =
+                self.precedences.get(precedence).or_else(|| {
+                    self.grammar
+                        .rules
+                        .get(precedence)
+                        .and_then(|rule| rule.disambiguation.as_ref())
+                });
+
+            match disambiguation {
+                Some(disambiguation) => {
+                    *production.disambiguation.borrow_mut() =
+                        Some(disambiguation.clone());
+                }
+                None => {
+                    undefined_precedences = true;
+                    errors.push(GrammarError::UndefinedPrecedence {
+                        rule_name:  rule_name.clone(),
+                        production: production.clone(),
+                        precedence: precedence.clone(),
+                    });
+                }
+            }
+        }
+
+        if undefined_rules || undefined_precedences {
+            return errors;
+        }
+
         self.compute_target_lexemes();
 
-        self.grammar.clone()
+        for rule in self.grammar.rules.values() {
+            for production in &rule.productions {
+                *production.target_char_classes.borrow_mut() = production
+                    .target_lexemes
+                    .borrow()
+                    .iter()
+                    .filter_map(|symbol| self.char_classes.get(symbol).cloned())
+                    .collect();
+            }
+        }
+
+        errors
     }
 }
 
@@ -344,6 +642,66 @@ macro_rules! __grammar_helper {
         );
     };
 
+    // rule_to_chars
+    (
+        $grammar:ident
+        $rule_name:literal
+        => chars $class:literal
+        => $production_action:expr
+    ) => {
+        $grammar.rule_to_chars($rule_name, $class, $production_action);
+    };
+    (
+        $grammar:ident
+        $rule_name:literal
+        => chars $class:literal
+    ) => {
+        santiago::__grammar_helper!(
+            $grammar
+            $rule_name
+            => chars $class
+            => |_| todo!("{}", $rule_name)
+        );
+    };
+
+    // rule_to_string
+    (
+        $grammar:ident
+        $rule_name:literal
+        => string $string:literal
+        => $production_action:expr
+    ) => {
+        $grammar.rule_to_string($rule_name, $string, $production_action);
+    };
+    (
+        $grammar:ident
+        $rule_name:literal
+        => string $string:literal
+    ) => {
+        santiago::__grammar_helper!(
+            $grammar
+            $rule_name
+            => string $string
+            => |_| todo!("{}", $rule_name)
+        );
+    };
+
+    // restrictions
+    (
+        $grammar:ident
+        $rule_name:literal
+        => not_followed_by $class:literal
+    ) => {
+        $grammar.not_followed_by($rule_name, $class);
+    };
+    (
+        $grammar:ident
+        $rule_name:literal
+        => reject $( $strings:literal )*
+    ) => {
+        $grammar.reject($rule_name, &[$( $strings ),*]);
+    };
+
     // disambiguate
     (
         $grammar:ident
@@ -359,6 +717,10 @@ macro_rules! __grammar_helper {
 
 /// Declarative utility for creating a [Grammar].
 ///
+/// A production may be followed by `prec "name"`
+/// in order to override its precedence,
+/// see [GrammarBuilder::precedence()].
+///
 /// Please read the [crate documentation](crate) for more information and examples.
 #[macro_export]
 macro_rules! grammar {
@@ -366,6 +728,7 @@ macro_rules! grammar {
         $(
             $rule_name:expr
             => $command:ident $( $production_symbols:literal )*
+            $( prec $precedence:literal )?
             $( => $production_action:expr )?
         );*
     ;) => {{
@@ -378,6 +741,7 @@ macro_rules! grammar {
                 => $command $( $production_symbols )*
                 $( => $production_action )?
             );
+            $( builder.precedence($precedence); )?
         )*
 
         builder.finish()
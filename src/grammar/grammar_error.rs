@@ -0,0 +1,192 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::grammar::Production;
+use std::rc::Rc;
+
+/// Internal representation of an error encountered
+/// by [GrammarBuilder::try_finish()](crate::grammar::GrammarBuilder::try_finish()).
+pub enum GrammarError<AST> {
+    /// The grammar does not define any rule.
+    Empty,
+    /// A production references a rule that has not been defined.
+    UndefinedRule {
+        /// Name of the rule that owns the production.
+        rule_name:  Rc<String>,
+        /// Production that references the undefined rule.
+        production: Rc<Production<AST>>,
+        /// Name of the rule that has not been defined.
+        symbol:     String,
+    },
+    /// A disambiguation references a rule that has not been previously defined,
+    /// and that has not been used as the precedence of a production.
+    UndefinedDisambiguation {
+        /// Name of the rule that has not been defined.
+        rule_name: String,
+    },
+    /// A precedence was given before defining any production,
+    /// or right after a production of kind
+    /// [ProductionKind::Lexemes](crate::grammar::ProductionKind::Lexemes),
+    /// to which precedences do not apply.
+    PrecedenceWithoutProduction {
+        /// Name of the precedence.
+        precedence: String,
+    },
+    /// A production uses a precedence that has not been disambiguated.
+    UndefinedPrecedence {
+        /// Name of the rule that owns the production.
+        rule_name:  Rc<String>,
+        /// Production that uses the precedence.
+        production: Rc<Production<AST>>,
+        /// Name of the precedence that has not been disambiguated.
+        precedence: String,
+    },
+    /// A character class is not valid.
+    InvalidCharClass {
+        /// Name of the rule that uses the character class.
+        rule_name: String,
+        /// The character class.
+        class:     String,
+        /// Human readable description of the problem.
+        message:   String,
+    },
+    /// A restriction references a rule that has not been previously defined.
+    UndefinedRestriction {
+        /// Name of the rule that has not been defined.
+        rule_name: String,
+    },
+    /// A rule defines the same production more than once.
+    DuplicateProduction {
+        /// Name of the rule that owns the production.
+        rule_name:  Rc<String>,
+        /// Production that has been defined more than once.
+        production: Rc<Production<AST>>,
+    },
+}
+
+impl<AST> std::clone::Clone for GrammarError<AST> {
+    fn clone(&self) -> GrammarError<AST> {
+        match self {
+            GrammarError::Empty => GrammarError::Empty,
+            GrammarError::UndefinedRule { rule_name, production, symbol } => {
+                GrammarError::UndefinedRule {
+                    rule_name:  rule_name.clone(),
+                    production: production.clone(),
+                    symbol:     symbol.clone(),
+                }
+            }
+            GrammarError::UndefinedDisambiguation { rule_name } => {
+                GrammarError::UndefinedDisambiguation {
+                    rule_name: rule_name.clone(),
+                }
+            }
+            GrammarError::PrecedenceWithoutProduction { precedence } => {
+                GrammarError::PrecedenceWithoutProduction {
+                    precedence: precedence.clone(),
+                }
+            }
+            GrammarError::UndefinedPrecedence {
+                rule_name,
+                production,
+                precedence,
+            } => GrammarError::UndefinedPrecedence {
+                rule_name:  rule_name.clone(),
+                production: production.clone(),
+                precedence: precedence.clone(),
+            },
+            GrammarError::InvalidCharClass { rule_name, class, message } => {
+                GrammarError::InvalidCharClass {
+                    rule_name: rule_name.clone(),
+                    class:     class.clone(),
+                    message:   message.clone(),
+                }
+            }
+            GrammarError::UndefinedRestriction { rule_name } => {
+                GrammarError::UndefinedRestriction {
+                    rule_name: rule_name.clone(),
+                }
+            }
+            GrammarError::DuplicateProduction { rule_name, production } => {
+                GrammarError::DuplicateProduction {
+                    rule_name:  rule_name.clone(),
+                    production: production.clone(),
+                }
+            }
+        }
+    }
+}
+
+impl<AST> std::fmt::Debug for GrammarError<AST> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl<AST> std::fmt::Display for GrammarError<AST> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrammarError::Empty => {
+                write!(f, "Your grammar does not define any rule.")
+            }
+            GrammarError::UndefinedRule { rule_name, production, symbol } => {
+                write!(
+                    f,
+                    "Error at rule: {rule_name}\nIn production: \
+                     {production}\nYour grammar references a rule with name: \
+                     {symbol}\nBut this rule has not been defined in the \
+                     grammar."
+                )
+            }
+            GrammarError::UndefinedDisambiguation { rule_name } => {
+                write!(
+                    f,
+                    "Error while trying to disambiguate a rule with name: \
+                     {rule_name}\nWhich has not been previously defined."
+                )
+            }
+            GrammarError::PrecedenceWithoutProduction { precedence } => {
+                write!(
+                    f,
+                    "Error while trying to use the precedence: \
+                     {precedence}\nNo production of kind Rules has been \
+                     previously defined."
+                )
+            }
+            GrammarError::UndefinedPrecedence {
+                rule_name,
+                production,
+                precedence,
+            } => {
+                write!(
+                    f,
+                    "Error at rule: {rule_name}\nIn production: \
+                     {production}\nYour grammar uses the precedence: \
+                     {precedence}\nBut it has not been disambiguated."
+                )
+            }
+            GrammarError::InvalidCharClass { rule_name, class, message } => {
+                write!(
+                    f,
+                    "Error at rule: {rule_name}\nThe character class: \
+                     {class}\nIs not valid: {message}"
+                )
+            }
+            GrammarError::UndefinedRestriction { rule_name } => {
+                write!(
+                    f,
+                    "Error while trying to restrict a rule with name: \
+                     {rule_name}\nWhich has not been previously defined."
+                )
+            }
+            GrammarError::DuplicateProduction { rule_name, production } => {
+                write!(
+                    f,
+                    "Error at rule: {rule_name}\nIn production: \
+                     {production}\nThis production has been defined more \
+                     than once."
+                )
+            }
+        }
+    }
+}
@@ -2,8 +2,10 @@
 //
 // SPDX-License-Identifier: GPL-3.0-only
 
+use crate::grammar::CharClass;
 use crate::grammar::Disambiguation;
 use crate::grammar::Production;
+use std::collections::HashSet;
 use std::hash::Hasher;
 use std::rc::Rc;
 
@@ -13,17 +15,23 @@ use std::rc::Rc;
 /// but normally you create a [GrammarRule]
 /// by using a [GrammarBuilder](crate::grammar::GrammarBuilder).
 pub struct GrammarRule<AST> {
-    pub(crate) name:           Rc<String>,
-    pub(crate) disambiguation: Option<Disambiguation>,
-    pub(crate) productions:    Vec<Rc<Production<AST>>>,
+    pub(crate) name:            Rc<String>,
+    pub(crate) disambiguation:  Option<Disambiguation>,
+    pub(crate) productions:     Vec<Rc<Production<AST>>>,
+    /// Characters that cannot follow this rule.
+    pub(crate) not_followed_by: Option<Rc<CharClass>>,
+    /// Strings that this rule cannot match.
+    pub(crate) rejects:         HashSet<String>,
 }
 
 impl<AST> std::clone::Clone for GrammarRule<AST> {
     fn clone(&self) -> GrammarRule<AST> {
         GrammarRule {
-            name:           self.name.clone(),
-            disambiguation: self.disambiguation.clone(),
-            productions:    self.productions.clone(),
+            name:            self.name.clone(),
+            disambiguation:  self.disambiguation.clone(),
+            productions:     self.productions.clone(),
+            not_followed_by: self.not_followed_by.clone(),
+            rejects:         self.rejects.clone(),
         }
     }
 }
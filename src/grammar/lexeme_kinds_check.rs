@@ -0,0 +1,84 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::grammar::Grammar;
+use crate::grammar::ProductionKind;
+use crate::lexer::LexerRules;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// Result of cross-checking the [Lexeme](crate::lexer::Lexeme) kinds
+/// that a [Grammar] expects
+/// against the ones that some [LexerRules] can emit.
+///
+/// Normally you create a [LexemeKindsCheck]
+/// by using [Grammar::check_lexer_rules()].
+pub struct LexemeKindsCheck {
+    /// Kinds that the [Grammar] expects but no lexer rule emits,
+    /// mapped to the names of the grammar rules that expect them.
+    ///
+    /// Productions that expect these kinds can never be matched.
+    pub missing_kinds: HashMap<String, HashSet<Rc<String>>>,
+    /// Names of the lexer rules whose kind the [Grammar] never consumes.
+    ///
+    /// Lexer rules that always skip or fail, like whitespace or comments,
+    /// are expected to appear here.
+    pub unused_kinds:  HashSet<String>,
+}
+
+impl LexemeKindsCheck {
+    pub(crate) fn new<AST, Context>(
+        grammar: &Grammar<AST>,
+        lexer_rules: &LexerRules<Context>,
+    ) -> LexemeKindsCheck {
+        let mut emitted_kinds: HashSet<&String> = lexer_rules
+            .rules
+            .values()
+            .flatten()
+            .map(|lexer_rule| &lexer_rule.name)
+            .collect();
+
+        if let Some(indentation) = &lexer_rules.indentation {
+            emitted_kinds.insert(&indentation.indent);
+            emitted_kinds.insert(&indentation.dedent);
+            emitted_kinds.insert(&indentation.newline);
+        }
+
+        let mut consumed_kinds: HashSet<&String> = HashSet::new();
+        let mut missing_kinds: HashMap<String, HashSet<Rc<String>>> =
+            HashMap::new();
+
+        for (rule_name, rule) in &grammar.rules {
+            for production in &rule.productions {
+                if let ProductionKind::Lexemes = production.kind {
+                    for kind in &production.symbols {
+                        consumed_kinds.insert(kind);
+
+                        if !emitted_kinds.contains(kind) {
+                            missing_kinds
+                                .entry(kind.clone())
+                                .or_default()
+                                .insert(rule_name.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        LexemeKindsCheck {
+            missing_kinds,
+            unused_kinds: emitted_kinds
+                .into_iter()
+                .filter(|kind| !consumed_kinds.contains(kind))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Whether the [Grammar] expects a kind that no lexer rule emits.
+    pub fn has_missing_kinds(&self) -> bool {
+        !self.missing_kinds.is_empty()
+    }
+}
@@ -7,15 +7,24 @@
 //! Please read the [crate documentation](crate) for more information and examples.
 
 mod associativity;
+mod char_class;
 mod disambiguation;
+mod grammar_analysis;
 mod grammar_builder;
+mod grammar_error;
 mod grammar_rule;
+mod lexeme_kinds_check;
 mod production;
 
+use crate::lexer::LexerRules;
 pub use associativity::Associativity;
+pub(crate) use char_class::CharClass;
 pub use disambiguation::Disambiguation;
+pub use grammar_analysis::GrammarAnalysis;
 pub use grammar_builder::GrammarBuilder;
+pub use grammar_error::GrammarError;
 pub use grammar_rule::GrammarRule;
+pub use lexeme_kinds_check::LexemeKindsCheck;
 pub use production::Production;
 pub use production::ProductionAction;
 pub use production::ProductionKind;
@@ -39,3 +48,20 @@ impl<AST> std::clone::Clone for Grammar<AST> {
         Grammar { rules: self.rules.clone() }
     }
 }
+
+impl<AST> Grammar<AST> {
+    /// Perform a static analysis of this [Grammar].
+    pub fn analyze(&self) -> GrammarAnalysis {
+        GrammarAnalysis::new(self)
+    }
+
+    /// Cross-check the [Lexeme](crate::lexer::Lexeme) kinds
+    /// that this [Grammar] expects
+    /// against the ones that `lexer_rules` can emit.
+    pub fn check_lexer_rules<Context>(
+        &self,
+        lexer_rules: &LexerRules<Context>,
+    ) -> LexemeKindsCheck {
+        LexemeKindsCheck::new(self, lexer_rules)
+    }
+}
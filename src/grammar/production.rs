@@ -2,6 +2,8 @@
 //
 // SPDX-License-Identifier: GPL-3.0-only
 
+use crate::grammar::CharClass;
+use crate::grammar::Disambiguation;
 use crate::lexer::Lexeme;
 use std::cell::RefCell;
 use std::collections::HashSet;
@@ -11,14 +13,43 @@ use std::rc::Rc;
 /// One possible derivation of a [GrammarRule](crate::grammar::GrammarRule).
 pub struct Production<AST> {
     /// Kind of symbols.
-    pub kind:                  ProductionKind,
+    pub kind: ProductionKind,
     /// Name of the [Grammar Rules](crate::grammar::GrammarRule)
     /// or [Lexemes](crate::lexer::Lexeme)
     /// that this [Production] may yield.
-    pub symbols:               Vec<String>,
+    pub symbols: Vec<String>,
     /// Action that this rule will perform at evaluation time.
-    pub action:                Rc<ProductionAction<AST>>,
+    pub action: Rc<ProductionAction<AST>>,
     pub(crate) target_lexemes: RefCell<HashSet<String>>,
+    pub(crate) disambiguation: RefCell<Option<Disambiguation>>,
+    /// Character class of the only symbol of this [Production],
+    /// if it was created with
+    /// [GrammarBuilder::rule_to_chars()](crate::grammar::GrammarBuilder::rule_to_chars()).
+    pub(crate) char_class: Option<Rc<CharClass>>,
+    /// Character classes among the `target_lexemes`.
+    pub(crate) target_char_classes: RefCell<Vec<Rc<CharClass>>>,
+}
+
+impl<AST> Production<AST> {
+    /// Whether a [Lexeme] of the given `kind`
+    /// matches the symbol at `index`.
+    pub(crate) fn matches(&self, index: usize, kind: &str) -> bool {
+        self.symbols[index] == kind
+            || self
+                .char_class
+                .as_ref()
+                .is_some_and(|char_class| char_class.matches(kind))
+    }
+
+    /// Whether this [Production] may start with a [Lexeme] of the given `kind`.
+    pub(crate) fn targets(&self, kind: &str) -> bool {
+        self.target_lexemes.borrow().contains(kind)
+            || self
+                .target_char_classes
+                .borrow()
+                .iter()
+                .any(|char_class| char_class.matches(kind))
+    }
 }
 
 impl<AST> std::fmt::Debug for Production<AST> {
@@ -58,6 +89,7 @@ impl<AST> std::hash::Hash for Production<AST> {
 }
 
 /// Action that a production will perform once evaluated.
+#[allow(clippy::type_complexity)]
 pub enum ProductionAction<AST> {
     /// Action to execute when this [Production] is of kind [ProductionKind::Lexemes].
     Lexemes(Rc<dyn Fn(&[&Rc<Lexeme>]) -> AST>),
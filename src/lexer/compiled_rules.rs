@@ -0,0 +1,178 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::LexerRule;
+use crate::lexer::LexerRuleSource;
+
+/// Rules of a [Lexer](crate::lexer::Lexer) state
+/// indexed so that they are not tried one by one:
+/// a prefix tree matches all the `string` rules at once,
+/// and a set of regular expressions tells which `pattern` rules
+/// need to run their own regular expression.
+///
+/// The set only filters the `pattern` rules:
+/// the length of their matches is still computed one by one,
+/// so that it is the one their own regular expression prefers.
+///
+/// Normally you create [CompiledRules]
+/// by using [LexerRules::compile()](crate::lexer::LexerRules::compile()).
+pub(crate) struct CompiledRules {
+    keywords: KeywordTrie,
+    #[cfg(feature = "crate_regex")]
+    patterns: Option<PatternSet>,
+}
+
+impl CompiledRules {
+    pub(crate) fn new<Context>(rules: &[LexerRule<Context>]) -> CompiledRules {
+        let mut keywords =
+            KeywordTrie { nodes: vec![KeywordTrieNode::default()] };
+
+        for (rule_index, rule) in rules.iter().enumerate() {
+            if let LexerRuleSource::String(string) = &rule.source {
+                keywords.insert(string, rule_index);
+            }
+        }
+
+        CompiledRules {
+            keywords,
+            #[cfg(feature = "crate_regex")]
+            patterns: PatternSet::new(rules),
+        }
+    }
+
+    /// Length of the longest match at the start of the `input`
+    /// and the index of the first rule that produces it,
+    /// or none if none of the `rules` match.
+    #[cfg_attr(not(feature = "crate_regex"), allow(unused_variables))]
+    pub(crate) fn longest_match<Context>(
+        &self,
+        rules: &[LexerRule<Context>],
+        input: &str,
+    ) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize)> = None;
+        let mut consider = |len: usize, rule_index: usize| {
+            if let Some((best_len, best_rule_index)) = best {
+                if best_len > len
+                    || best_len == len && best_rule_index < rule_index
+                {
+                    return;
+                }
+            }
+
+            best = Some((len, rule_index));
+        };
+
+        self.keywords.matches(input, &mut consider);
+
+        #[cfg(feature = "crate_regex")]
+        if let Some(patterns) = &self.patterns {
+            for index in patterns.set.matches(input).iter() {
+                let rule_index = patterns.rule_indexes[index];
+
+                if let Some(len) = (rules[rule_index].matcher)(input) {
+                    consider(len, rule_index);
+                }
+            }
+        }
+
+        best
+    }
+}
+
+/// Prefix tree of the strings matched by the `string` rules.
+struct KeywordTrie {
+    nodes: Vec<KeywordTrieNode>,
+}
+
+#[derive(Default)]
+struct KeywordTrieNode {
+    /// Next byte and index of the node it leads to, sorted by byte.
+    children: Vec<(u8, usize)>,
+    /// Indexes of the rules whose string ends at this node.
+    rules:    Vec<usize>,
+}
+
+impl KeywordTrie {
+    fn insert(&mut self, string: &str, rule_index: usize) {
+        let mut node_index = 0;
+
+        for byte in string.bytes() {
+            let children = &self.nodes[node_index].children;
+
+            node_index = match children.binary_search_by_key(&byte, |c| c.0) {
+                Ok(position) => children[position].1,
+                Err(position) => {
+                    let new_node_index = self.nodes.len();
+                    self.nodes[node_index]
+                        .children
+                        .insert(position, (byte, new_node_index));
+                    self.nodes.push(KeywordTrieNode::default());
+
+                    new_node_index
+                }
+            };
+        }
+
+        self.nodes[node_index].rules.push(rule_index);
+    }
+
+    /// Call `consider` with the length and rule index
+    /// of every string that is a prefix of the `input`.
+    fn matches(&self, input: &str, consider: &mut impl FnMut(usize, usize)) {
+        let mut node_index = 0;
+        let mut len = 0;
+
+        loop {
+            for rule_index in &self.nodes[node_index].rules {
+                consider(len, *rule_index);
+            }
+
+            let byte = match input.as_bytes().get(len) {
+                Some(byte) => byte,
+                None => return,
+            };
+            let children = &self.nodes[node_index].children;
+
+            node_index = match children.binary_search_by_key(byte, |c| c.0) {
+                Ok(position) => children[position].1,
+                Err(_) => return,
+            };
+            len += 1;
+        }
+    }
+}
+
+/// Patterns of the `pattern` rules,
+/// used to find which of them match
+/// before computing the length of their matches.
+#[cfg(feature = "crate_regex")]
+struct PatternSet {
+    set:          crate_regex::RegexSet,
+    /// Index of the rule of each pattern in `set`.
+    rule_indexes: Vec<usize>,
+}
+
+#[cfg(feature = "crate_regex")]
+impl PatternSet {
+    fn new<Context>(rules: &[LexerRule<Context>]) -> Option<PatternSet> {
+        let mut patterns = Vec::new();
+        let mut rule_indexes = Vec::new();
+
+        for (rule_index, rule) in rules.iter().enumerate() {
+            if let LexerRuleSource::Pattern(pattern) = &rule.source {
+                patterns.push(format!(r"\A(?:{pattern})"));
+                rule_indexes.push(rule_index);
+            }
+        }
+
+        if patterns.is_empty() {
+            None
+        } else {
+            // Patterns were already validated by the LexerBuilder.
+            let set = crate_regex::RegexSet::new(patterns).unwrap();
+
+            Some(PatternSet { set, rule_indexes })
+        }
+    }
+}
@@ -0,0 +1,300 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::Lexeme;
+use crate::lexer::Lexer;
+use crate::lexer::NextLexeme;
+
+/// Configuration for indentation-sensitive lexing,
+/// as in languages like Python or YAML.
+///
+/// In the states where it is enabled,
+/// the [Lexer] consumes the newlines and the indentation of each line,
+/// before trying any rule,
+/// and emits:
+/// - A [Lexeme] of kind [Indentation::newline]
+///   at the end of each line that produced other [Lexeme]s.
+/// - A [Lexeme] of kind [Indentation::indent]
+///   when a line is more indented than the previous one.
+/// - One [Lexeme] of kind [Indentation::dedent]
+///   for every indentation level that a line closes.
+///
+/// Blank lines, lines that start with any of the [Indentation::comments],
+/// and lines inside brackets are joined with the previous line,
+/// so they do not change the indentation.
+/// At the end of the input, the last line is terminated
+/// and all the open indentation levels are closed.
+///
+/// The rules of these states should not match newlines,
+/// otherwise the [Lexer] does not see them.
+///
+/// Normally you enable it with
+/// [LexerBuilder::indentation()](crate::lexer::LexerBuilder::indentation()).
+#[derive(Clone, Debug)]
+pub struct Indentation {
+    /// Kind of the [Lexeme]s that open an indentation level.
+    pub indent:         String,
+    /// Kind of the [Lexeme]s that close an indentation level.
+    pub dedent:         String,
+    /// Kind of the [Lexeme]s that terminate a line.
+    pub newline:        String,
+    /// How tabs are counted in the indentation.
+    pub tabs:           Tabs,
+    /// Kinds of the [Lexeme]s that open a bracket.
+    pub open_brackets:  Vec<String>,
+    /// Kinds of the [Lexeme]s that close a bracket.
+    pub close_brackets: Vec<String>,
+    /// Prefixes of the lines that are ignored for indentation purposes.
+    pub comments:       Vec<String>,
+}
+
+impl Default for Indentation {
+    /// Python-like indentation,
+    /// with `INDENT`, `DEDENT` and `NEWLINE` [Lexeme]s,
+    /// tabs that advance to the next multiple of 8,
+    /// `(`, `[` and `{` brackets,
+    /// and `#` comments.
+    fn default() -> Indentation {
+        Indentation {
+            indent:         "INDENT".to_string(),
+            dedent:         "DEDENT".to_string(),
+            newline:        "NEWLINE".to_string(),
+            tabs:           Tabs::Width(8),
+            open_brackets:  vec![
+                "(".to_string(),
+                "[".to_string(),
+                "{".to_string(),
+            ],
+            close_brackets: vec![
+                ")".to_string(),
+                "]".to_string(),
+                "}".to_string(),
+            ],
+            comments:       vec!["#".to_string()],
+        }
+    }
+}
+
+/// How tabs are counted in the [Indentation].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Tabs {
+    /// Tabs are not allowed in the indentation.
+    Forbid,
+    /// A tab advances the indentation to the next multiple of this width,
+    /// which must be greater than zero.
+    Width(usize),
+}
+
+/// State of the [Indentation] while lexing.
+#[derive(Clone, Eq, PartialEq)]
+pub(crate) struct IndentationState {
+    /// Widths of the open indentation levels, starting with `0`.
+    levels:           Vec<usize>,
+    /// Number of brackets that are open.
+    brackets:         usize,
+    at_line_start:    bool,
+    /// Whether the current line produced any [Lexeme].
+    line_has_content: bool,
+    /// Whether the end of the input was already processed.
+    closed:           bool,
+}
+
+impl IndentationState {
+    pub(crate) fn new() -> IndentationState {
+        IndentationState {
+            levels:           vec![0],
+            brackets:         0,
+            at_line_start:    true,
+            line_has_content: false,
+            closed:           false,
+        }
+    }
+}
+
+impl<'a, Context> Lexer<'a, Context> {
+    /// Consume the newline or indentation at the current position,
+    /// queueing the [Lexeme]s they produce,
+    /// or none if the rules should be tried instead.
+    pub(crate) fn indent(&mut self) -> Option<NextLexeme> {
+        let rules = self.rules;
+        let indentation = rules.indentation.as_ref()?;
+        let state = *self.states_stack.back().unwrap();
+
+        if !self.is_indentation_state(state) {
+            return None;
+        }
+
+        let input = &self.input[self.current_byte_index..];
+
+        if let Some(len) = newline_len(input) {
+            let position = self.position.clone();
+            self.current_match_len = len;
+            self.current_rule_name = &indentation.newline;
+
+            if self.indentation.brackets == 0
+                && self.indentation.line_has_content
+            {
+                self.advance();
+                self.pending.push_back(Lexeme {
+                    kind: indentation.newline.clone(),
+                    raw: input[..len].to_string(),
+                    position,
+                    position_end: self.position.clone(),
+                    leading_trivia: Vec::new(),
+                    trailing_trivia: Vec::new(),
+                });
+                self.indentation.line_has_content = false;
+            } else {
+                self.skip();
+            }
+            self.indentation.at_line_start = true;
+
+            return Some(NextLexeme::Skip);
+        }
+
+        if !self.indentation.at_line_start {
+            return None;
+        }
+        self.indentation.at_line_start = false;
+
+        let len = input.len() - input.trim_start_matches([' ', '\t']).len();
+        let rest = &input[len..];
+        self.current_match_len = len;
+        self.current_rule_name = &indentation.indent;
+
+        if self.indentation.brackets > 0
+            || rest.is_empty()
+            || newline_len(rest).is_some()
+            || indentation
+                .comments
+                .iter()
+                .any(|comment| rest.starts_with(comment.as_str()))
+        {
+            return Some(self.skip());
+        }
+
+        let mut width = 0;
+        for char in input[..len].chars() {
+            width = match (char, &indentation.tabs) {
+                (' ', _) => width + 1,
+                (_, Tabs::Width(tab_width)) => {
+                    (width / tab_width + 1) * tab_width
+                }
+                (_, Tabs::Forbid) => {
+                    return Some(
+                        self.error("Tabs are not allowed in the indentation"),
+                    );
+                }
+            };
+        }
+
+        let levels = &mut self.indentation.levels;
+        let indents = width > *levels.last().unwrap();
+        let mut dedents = 0;
+
+        if indents {
+            levels.push(width);
+        } else if !levels.contains(&width) {
+            return Some(
+                self.error(
+                    "Unindent does not match any outer indentation level",
+                ),
+            );
+        } else {
+            while width < *levels.last().unwrap() {
+                levels.pop();
+                dedents += 1;
+            }
+        }
+
+        let position = self.position.clone();
+
+        // The indentation is the contents of the INDENT lexeme, if any.
+        if indents {
+            self.advance();
+            self.pending.push_back(Lexeme {
+                kind: indentation.indent.clone(),
+                raw: input[..len].to_string(),
+                position,
+                position_end: self.position.clone(),
+                leading_trivia: Vec::new(),
+                trailing_trivia: Vec::new(),
+            });
+        } else {
+            self.skip();
+        }
+        for _ in 0..dedents {
+            self.pending.push_back(self.synthetic(&indentation.dedent));
+        }
+
+        Some(NextLexeme::Skip)
+    }
+
+    /// Keep track of the lines and brackets
+    /// given a [Lexeme] produced by the rules
+    /// that were active in the given `state`.
+    pub(crate) fn track_indentation(&mut self, lexeme: &Lexeme, state: &str) {
+        let indentation = match &self.rules.indentation {
+            Some(indentation) => indentation,
+            None => return,
+        };
+
+        self.indentation.line_has_content = true;
+
+        // Brackets inside strings or comments do not count.
+        if !self.is_indentation_state(state) {
+            return;
+        }
+
+        if indentation.open_brackets.contains(&lexeme.kind) {
+            self.indentation.brackets += 1;
+        } else if indentation.close_brackets.contains(&lexeme.kind) {
+            self.indentation.brackets =
+                self.indentation.brackets.saturating_sub(1);
+        }
+    }
+
+    /// Whether the indentation is tracked in the given `state`.
+    fn is_indentation_state(&self, state: &str) -> bool {
+        self.rules.indentation_states.iter().any(|state_| state_ == state)
+    }
+
+    /// Queue the [Lexeme]s that terminate the last line
+    /// and close the open indentation levels at the end of the input.
+    ///
+    /// Return whether any [Lexeme] was queued.
+    pub(crate) fn dedent_at_end(&mut self) -> bool {
+        let indentation = match &self.rules.indentation {
+            Some(indentation) => indentation,
+            None => return false,
+        };
+
+        if self.indentation.closed {
+            return false;
+        }
+        self.indentation.closed = true;
+
+        if self.indentation.line_has_content {
+            self.pending.push_back(self.synthetic(&indentation.newline));
+        }
+
+        while self.indentation.levels.len() > 1 {
+            self.indentation.levels.pop();
+            self.pending.push_back(self.synthetic(&indentation.dedent));
+        }
+
+        !self.pending.is_empty()
+    }
+}
+
+/// Length of the newline at the start of the `input`, if any.
+fn newline_len(input: &str) -> Option<usize> {
+    if input.starts_with('\n') {
+        Some(1)
+    } else if input.starts_with("\r\n") {
+        Some(2)
+    } else {
+        None
+    }
+}
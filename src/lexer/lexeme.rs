@@ -3,6 +3,8 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::lexer::Position;
+use crate::lexer::Trivia;
+use std::ops::Range;
 
 /// Represents a group or related characters and its position.
 ///
@@ -11,13 +13,53 @@ use crate::lexer::Position;
 pub struct Lexeme {
     /// Name of the [LexerRules](crate::lexer::LexerRules)
     /// that produced this [Lexeme].
-    pub kind:     String,
+    pub kind:            String,
     /// Raw content of this [Lexeme],
     /// as matched by the [Lexer](crate::lexer::Lexer).
-    pub raw:      String,
-    /// [Position] of this [Lexeme] relative to the input
+    pub raw:             String,
+    /// [Position] where this [Lexeme] starts, relative to the input
     /// passed to the [Lexer](crate::lexer::Lexer).
-    pub position: Position,
+    pub position:        Position,
+    /// [Position] where this [Lexeme] ends (exclusive), relative to the input
+    /// passed to the [Lexer](crate::lexer::Lexer).
+    pub position_end:    Position,
+    /// [Trivia] skipped right before this [Lexeme].
+    pub leading_trivia:  Vec<Trivia>,
+    /// [Trivia] skipped right after this [Lexeme],
+    /// only for the last [Lexeme] of the input.
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+impl Lexeme {
+    /// Range of bytes of the input
+    /// passed to the [Lexer](crate::lexer::Lexer)
+    /// that produced this [Lexeme].
+    ///
+    /// Note that this range covers what was consumed from the input,
+    /// which may differ from [Lexeme::raw]
+    /// if the contents were mapped with
+    /// [take_and_map](crate::lexer::Lexer::take_and_map()),
+    /// and which is empty if the match is retried with
+    /// [take_and_retry](crate::lexer::Lexer::take_and_retry()).
+    pub fn byte_range(&self) -> Range<usize> {
+        self.position.byte_index..self.position_end.byte_index
+    }
+
+    /// [Lexeme::raw] surrounded by the raw contents
+    /// of its leading and trailing [Trivia].
+    pub fn text_with_trivia(&self) -> String {
+        let mut text = String::new();
+
+        for trivia in &self.leading_trivia {
+            text.push_str(&trivia.raw);
+        }
+        text.push_str(&self.raw);
+        for trivia in &self.trailing_trivia {
+            text.push_str(&trivia.raw);
+        }
+
+        text
+    }
 }
 
 impl std::fmt::Display for Lexeme {
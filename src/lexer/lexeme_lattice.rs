@@ -0,0 +1,285 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::IndentationState;
+use crate::lexer::Lexeme;
+use crate::lexer::Lexer;
+use crate::lexer::LexerError;
+use crate::lexer::LexerRules;
+use crate::lexer::NextLexeme;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::LinkedList;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// All the alternative ways of splitting an input into [Lexeme]s,
+/// as a directed acyclic graph
+/// whose edges are [Lexeme]s and whose nodes are positions between them.
+///
+/// Every path from the node `0` to one of the final nodes
+/// is a tokenisation of the whole input.
+///
+/// Normally you create a [LexemeLattice] by using [lex_lattice()],
+/// and parse it with [parse_lattice()](crate::parser::parse_lattice()).
+pub struct LexemeLattice {
+    /// Number of nodes, sorted so that edges go from a node to a later one.
+    pub(crate) len:    usize,
+    /// [Lexeme]s and the nodes they go from and to, sorted by the former.
+    pub(crate) edges:  Vec<(usize, usize, Rc<Lexeme>)>,
+    /// Nodes where the input ends.
+    pub(crate) finals: BTreeSet<usize>,
+}
+
+impl LexemeLattice {
+    /// Number of nodes in this [LexemeLattice].
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether this [LexemeLattice] has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// [Lexeme]s of this [LexemeLattice]
+    /// together with the nodes they go from and to.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, &Rc<Lexeme>)> {
+        self.edges.iter().map(|(from, to, lexeme)| (*from, *to, lexeme))
+    }
+
+    /// Whether the input ends at the given node.
+    pub fn is_final(&self, node: usize) -> bool {
+        self.finals.contains(&node)
+    }
+
+    /// Every tokenisation of the whole input, in order.
+    ///
+    /// Note that their number can grow exponentially with the input length.
+    pub fn tokenisations(&self) -> Vec<Vec<Rc<Lexeme>>> {
+        let mut tokenisations = Vec::new();
+        let mut stack = vec![(0, Vec::new())];
+
+        while let Some((node, lexemes)) = stack.pop() {
+            if self.is_final(node) {
+                tokenisations.push(lexemes.clone());
+            }
+
+            for (from, to, lexeme) in self.edges.iter().rev() {
+                if *from == node {
+                    let mut lexemes = lexemes.clone();
+                    lexemes.push(lexeme.clone());
+                    stack.push((*to, lexemes));
+                }
+            }
+        }
+
+        tokenisations
+    }
+}
+
+impl std::fmt::Debug for LexemeLattice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl std::fmt::Display for LexemeLattice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (from, to, lexeme) in &self.edges {
+            writeln!(f, "{from} -> {to} {lexeme}")?;
+        }
+
+        write!(f, "Finals: {:?}", self.finals)
+    }
+}
+
+impl<'a> Lexer<'a> {
+    /// Copy of the position and states of this [Lexer].
+    fn fork(&self) -> Lexer<'a> {
+        Lexer {
+            rules:              self.rules,
+            input:              self.input,
+            current_byte_index: self.current_byte_index,
+            current_match_len:  0,
+            current_rule_name:  "",
+            position:           self.position.clone(),
+            states_stack:       self.states_stack.clone(),
+            finished:           false,
+            pending:            VecDeque::new(),
+            trivia:             self.trivia.clone(),
+            indentation:        IndentationState::new(),
+            expected_kinds:     None,
+            context:            (),
+        }
+    }
+
+    fn key(&self) -> (usize, LinkedList<&'a str>) {
+        (self.current_byte_index, self.states_stack.clone())
+    }
+}
+
+/// Perform lexical analysis of the given input according to the provided rules,
+/// keeping all the alternative tokenisations.
+///
+/// Where the [Lexer] would take the longest match and the first rule,
+/// this function also tries every other rule that matches.
+/// Alternatives whose action signals an error are dropped.
+///
+/// [Indentation](crate::lexer::Indentation) is not applied.
+///
+/// Returns the error found the furthest in the input
+/// if no tokenisation reaches the end of the input.
+pub fn lex_lattice(
+    rules: &LexerRules,
+    input: &str,
+) -> Result<LexemeLattice, LexerError> {
+    let mut nodes: Vec<Lexer> = vec![Lexer::new(rules, input)];
+    let mut node_indexes = HashMap::from([(nodes[0].key(), 0)]);
+    // Nodes not expanded yet, sorted by position in the input.
+    let mut queue = BTreeSet::from([(0, 0)]);
+    // Position of each node in the final order, once expanded.
+    let mut order: Vec<Option<usize>> = vec![None];
+    let mut expanded = 0;
+
+    let mut edges = Vec::new();
+    let mut finals = BTreeSet::new();
+    let mut furthest_error: Option<LexerError> = None;
+    let mut record_error = |error: LexerError| match &furthest_error {
+        Some(furthest) if furthest.byte_index >= error.byte_index => {}
+        _ => furthest_error = Some(error),
+    };
+
+    while let Some((_, node)) = queue.pop_first() {
+        order[node] = Some(expanded);
+        expanded += 1;
+
+        // The node and the nodes reachable from it by skipping the input.
+        let mut closure = vec![nodes[node].fork()];
+        let mut closure_keys = HashSet::from([nodes[node].key()]);
+
+        while let Some(lexer) = closure.pop() {
+            let state = *lexer.states_stack.back().unwrap();
+
+            if lexer.current_byte_index == input.len() && state == "DEFAULT" {
+                finals.insert(node);
+                continue;
+            }
+
+            let active_rules = match rules.rules.get(state) {
+                Some(rules) => rules.as_slice(),
+                None => &[],
+            };
+            let rest = &input[lexer.current_byte_index..];
+            let mut matched = false;
+
+            for rule in active_rules {
+                let len = match (rule.matcher)(rest) {
+                    Some(len) => len,
+                    None => continue,
+                };
+                matched = true;
+
+                let mut fork = lexer.fork();
+                fork.current_match_len = len;
+                fork.current_rule_name = &rule.name;
+                let position = fork.position.clone();
+
+                let mut lexemes = match rule.action.clone()(&mut fork) {
+                    NextLexeme::Lexeme { kind, raw } => {
+                        vec![fork.lexeme(position, kind, raw)]
+                    }
+                    NextLexeme::Lexemes(lexemes) => lexemes,
+                    NextLexeme::Skip => Vec::new(),
+                    NextLexeme::Error(error) => {
+                        record_error(error);
+                        continue;
+                    }
+                    NextLexeme::Finished => continue,
+                };
+
+                if lexemes.is_empty() {
+                    if closure_keys.insert(fork.key()) {
+                        closure.push(fork);
+                    }
+                    continue;
+                }
+
+                lexemes[0].leading_trivia.splice(0..0, fork.trivia.drain(..));
+
+                let target = match node_indexes.get(&fork.key()) {
+                    // Edges to expanded nodes would form a cycle.
+                    Some(target) if order[*target].is_some() => continue,
+                    Some(target) => *target,
+                    None => {
+                        let target = nodes.len();
+                        node_indexes.insert(fork.key(), target);
+                        queue.insert((fork.current_byte_index, target));
+                        nodes.push(fork);
+                        order.push(None);
+                        target
+                    }
+                };
+
+                // Several lexemes of a single match go through new nodes
+                // that come right after this one.
+                let mut from = node;
+                let last = lexemes.len() - 1;
+                for (index, lexeme) in lexemes.into_iter().enumerate() {
+                    let to = if index == last {
+                        target
+                    } else {
+                        nodes.push(nodes[node].fork());
+                        order.push(Some(expanded));
+                        expanded += 1;
+                        nodes.len() - 1
+                    };
+
+                    edges.push((from, to, Rc::new(lexeme)));
+                    from = to;
+                }
+            }
+
+            if !matched {
+                let mut fork = lexer.fork();
+                if let NextLexeme::Error(error) = fork.next_lexeme() {
+                    record_error(error);
+                }
+            }
+        }
+    }
+
+    if finals.is_empty() {
+        return Err(furthest_error.unwrap_or_else(|| {
+            match Lexer::new(rules, input)
+                .error("No tokenisation reaches the end of the input")
+            {
+                NextLexeme::Error(error) => error,
+                _ => unreachable!(),
+            }
+        }));
+    }
+
+    // Number the nodes in the order they were expanded,
+    // and keep only the edges that lead to the end of the input.
+    let order: Vec<usize> = order.into_iter().map(Option::unwrap).collect();
+    let mut edges: Vec<(usize, usize, Rc<Lexeme>)> = edges
+        .into_iter()
+        .map(|(from, to, lexeme)| (order[from], order[to], lexeme))
+        .collect();
+    let finals: BTreeSet<usize> =
+        finals.into_iter().map(|node| order[node]).collect();
+
+    let mut useful: HashSet<usize> = finals.iter().copied().collect();
+    edges.sort_by_key(|(from, to, _)| (*from, *to));
+    for (from, to, _) in edges.iter().rev() {
+        if useful.contains(to) {
+            useful.insert(*from);
+        }
+    }
+    edges.retain(|(_, to, _)| useful.contains(to));
+
+    Ok(LexemeLattice { len: expanded, edges, finals })
+}
@@ -2,28 +2,53 @@
 //
 // SPDX-License-Identifier: GPL-3.0-only
 
+use crate::lexer::Indentation;
 use crate::lexer::Lexer;
+use crate::lexer::LexerBuilderError;
 use crate::lexer::LexerRule;
+use crate::lexer::LexerRuleSource;
 use crate::lexer::LexerRules;
 use crate::lexer::NextLexeme;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::rc::Rc;
 
 /// Imperative utility for creating [LexerRules].
 ///
 /// Please read the [crate documentation](crate) for more information and examples.
-pub struct LexerBuilder {
-    rules: LexerRules,
+pub struct LexerBuilder<Context = ()> {
+    declared_states: HashSet<String>,
+    errors:          Vec<LexerBuilderError>,
+    rules:           LexerRules<Context>,
 }
 
 impl LexerBuilder {
     /// Creates a new [LexerBuilder] with no rules.
     #[allow(clippy::new_without_default)]
     pub fn new() -> LexerBuilder {
-        LexerBuilder { rules: LexerRules { rules: HashMap::new() } }
+        LexerBuilder::with_context()
+    }
+}
+
+impl<Context> LexerBuilder<Context> {
+    /// Creates a new [LexerBuilder] with no rules,
+    /// whose actions receive a [Lexer] with a context of type `Context`.
+    ///
+    /// See [lex_with_context()](crate::lexer::lex_with_context()).
+    pub fn with_context() -> LexerBuilder<Context> {
+        LexerBuilder {
+            declared_states: HashSet::new(),
+            errors:          Vec::new(),
+            rules:           LexerRules {
+                rules:              HashMap::new(),
+                compiled:           HashMap::new(),
+                indentation:        None,
+                indentation_states: Vec::new(),
+            },
+        }
     }
 
-    fn insert(&mut self, states: &[&str], rule: LexerRule) {
+    fn insert(&mut self, states: &[&str], rule: LexerRule<Context>) {
         for state in states {
             let state = state.to_string();
 
@@ -50,20 +75,22 @@ impl LexerBuilder {
         states: &[&str],
         name: &str,
         string: &'static str,
-        action: fn(&mut Lexer) -> NextLexeme,
-    ) -> &mut LexerBuilder {
+        action: impl Fn(&mut Lexer<Context>) -> NextLexeme + 'static,
+    ) -> &mut LexerBuilder<Context> {
         self.insert(
             states,
             LexerRule {
                 action:  Rc::new(action),
                 matcher: Rc::new(move |input: &str| -> Option<usize> {
-                    if input.starts_with(&string) {
+                    if input.starts_with(string) {
                         Some(string.len())
                     } else {
                         None
                     }
                 }),
+                max_len: Some(string.len()),
                 name:    name.to_string(),
+                source:  LexerRuleSource::String(string.to_string()),
             },
         );
 
@@ -75,16 +102,37 @@ impl LexerBuilder {
     /// with name `name`,
     /// that matches the regular expression `pattern`,
     /// and performs the provided `action`.
+    ///
+    /// If `pattern` is not a valid regular expression
+    /// the rule is not added,
+    /// and the error is reported by [LexerBuilder::finish()]
+    /// and [LexerBuilder::try_finish()].
     #[cfg(feature = "crate_regex")]
     pub fn pattern(
         &mut self,
         states: &[&str],
         name: &str,
         pattern: &str,
-        action: fn(&mut Lexer) -> NextLexeme,
-    ) -> &mut LexerBuilder {
-        let regex =
-            crate_regex::Regex::new(&format!(r"\A(?:{pattern})")).unwrap();
+        action: impl Fn(&mut Lexer<Context>) -> NextLexeme + 'static,
+    ) -> &mut LexerBuilder<Context> {
+        let anchored = format!(r"\A(?:{pattern})");
+        let regex = crate_regex::Regex::new(&anchored);
+        let regex = match regex {
+            Ok(regex) => regex,
+            Err(error) => {
+                self.errors.push(LexerBuilderError::InvalidPattern {
+                    rule_name: name.to_string(),
+                    states:    states
+                        .iter()
+                        .map(|state| state.to_string())
+                        .collect(),
+                    pattern:   pattern.to_string(),
+                    message:   error.to_string(),
+                });
+
+                return self;
+            }
+        };
 
         self.insert(
             states,
@@ -97,17 +145,231 @@ impl LexerBuilder {
                         .map(|match_| match_.end())
                         .next()
                 }),
+                max_len: crate_regex_syntax::Parser::new()
+                    .parse(&anchored)
+                    .ok()
+                    .and_then(|hir| max_len(&hir)),
                 name:    name.to_string(),
+                source:  LexerRuleSource::Pattern(pattern.to_string()),
             },
         );
 
         self
     }
 
+    /// Enable indentation-sensitive lexing
+    /// when the current [Lexer] state matches any of `states`,
+    /// according to the provided [Indentation].
+    ///
+    /// Replaces any previous [Indentation].
+    pub fn indentation(
+        &mut self,
+        states: &[&str],
+        indentation: Indentation,
+    ) -> &mut LexerBuilder<Context> {
+        self.rules.indentation = Some(indentation);
+        self.rules.indentation_states =
+            states.iter().map(|state| state.to_string()).collect();
+
+        self
+    }
+
+    /// Declare that rule actions may push `states`
+    /// with [Lexer::push_state()].
+    ///
+    /// Rule actions are opaque closures,
+    /// so the states they push can not be inferred.
+    /// [LexerBuilder::try_finish()] reports the rules
+    /// that are active in a state that is neither `"DEFAULT"` nor declared.
+    pub fn declare_states(
+        &mut self,
+        states: &[&str],
+    ) -> &mut LexerBuilder<Context> {
+        for state in states {
+            self.declared_states.insert(state.to_string());
+        }
+
+        self
+    }
+
     /// Return the created [LexerRules].
-    pub fn finish(&self) -> LexerRules {
+    ///
+    /// Panics if a pattern is not a valid regular expression.
+    /// Rules that can never be selected are not reported,
+    /// see [LexerBuilder::try_finish()] for a fallible alternative
+    /// that performs all the validations.
+    pub fn finish(&self) -> LexerRules<Context> {
+        if !self.errors.is_empty() {
+            panic!(
+                "\n\n{}\n\n",
+                self.errors
+                    .iter()
+                    .map(LexerBuilderError::to_string)
+                    .collect::<Vec<String>>()
+                    .join("\n\n")
+            );
+        }
+
         self.rules.clone()
     }
+
+    /// Return the created [LexerRules], performing a few validations first:
+    ///
+    /// - Every pattern is a valid regular expression.
+    /// - Every state other than `"DEFAULT"` in which a rule is active
+    ///   has been declared with [LexerBuilder::declare_states()].
+    ///   Note that this check also applies when no state has been declared.
+    /// - No rule is shadowed by a rule declared before it in the same state.
+    ///   Patterns that contain assertions like `\b` or `$`
+    ///   are never considered to shadow other rules,
+    ///   because whether they match depends on the surrounding input.
+    ///
+    /// Returns all the [LexerBuilderError]s found
+    /// if the [LexerRules] are not valid.
+    pub fn try_finish(
+        &self,
+    ) -> Result<LexerRules<Context>, Vec<LexerBuilderError>> {
+        let mut errors = self.errors.clone();
+
+        let mut states: Vec<&String> = self.rules.rules.keys().collect();
+        states.sort();
+
+        for state in states {
+            let rules = &self.rules.rules[state];
+
+            if state != "DEFAULT" && !self.declared_states.contains(state) {
+                for rule in rules {
+                    errors.push(LexerBuilderError::UnreachableState {
+                        rule_name: rule.name.clone(),
+                        state:     state.clone(),
+                    });
+                }
+            }
+
+            for (index, rule) in rules.iter().enumerate() {
+                if let Some(shadowed_by) = rules[..index]
+                    .iter()
+                    .find(|earlier_rule| shadows(earlier_rule, rule))
+                {
+                    errors.push(LexerBuilderError::ShadowedRule {
+                        rule_name:   rule.name.clone(),
+                        state:       state.clone(),
+                        shadowed_by: shadowed_by.name.clone(),
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self.rules.clone())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Whether every match of `rule` is also matched, at least as long,
+/// by `earlier_rule`.
+fn shadows<Context>(
+    earlier_rule: &LexerRule<Context>,
+    rule: &LexerRule<Context>,
+) -> bool {
+    match (&earlier_rule.source, &rule.source) {
+        (LexerRuleSource::Pattern(earlier_pattern), _)
+            if has_assertions(earlier_pattern) =>
+        {
+            false
+        }
+        (_, LexerRuleSource::String(string)) => {
+            (earlier_rule.matcher)(string) == Some(string.len())
+        }
+        (
+            LexerRuleSource::Pattern(earlier_pattern),
+            LexerRuleSource::Pattern(pattern),
+        ) => earlier_pattern == pattern,
+        (LexerRuleSource::String(_), LexerRuleSource::Pattern(_)) => false,
+    }
+}
+
+/// Maximum length in bytes of the matches of `hir`,
+/// or none if they can be arbitrarily long.
+#[cfg(feature = "crate_regex")]
+fn max_len(hir: &crate_regex_syntax::hir::Hir) -> Option<usize> {
+    use crate_regex_syntax::hir::Class;
+    use crate_regex_syntax::hir::HirKind;
+    use crate_regex_syntax::hir::Literal;
+    use crate_regex_syntax::hir::RepetitionKind;
+    use crate_regex_syntax::hir::RepetitionRange;
+
+    match hir.kind() {
+        HirKind::Empty | HirKind::Anchor(_) | HirKind::WordBoundary(_) => {
+            Some(0)
+        }
+        HirKind::Literal(Literal::Unicode(char)) => Some(char.len_utf8()),
+        HirKind::Literal(Literal::Byte(_)) => Some(1),
+        HirKind::Class(Class::Unicode(class)) => Some(
+            class.ranges().last().map_or(0, |range| range.end().len_utf8()),
+        ),
+        HirKind::Class(Class::Bytes(_)) => Some(1),
+        HirKind::Repetition(repetition) => {
+            let times = match &repetition.kind {
+                RepetitionKind::ZeroOrOne => 1,
+                RepetitionKind::Range(RepetitionRange::Exactly(times))
+                | RepetitionKind::Range(RepetitionRange::Bounded(_, times)) => {
+                    *times as usize
+                }
+                RepetitionKind::ZeroOrMore
+                | RepetitionKind::OneOrMore
+                | RepetitionKind::Range(RepetitionRange::AtLeast(_)) => {
+                    return None;
+                }
+            };
+
+            max_len(&repetition.hir)?.checked_mul(times)
+        }
+        HirKind::Group(group) => max_len(&group.hir),
+        HirKind::Concat(hirs) => hirs
+            .iter()
+            .try_fold(0_usize, |len, hir| len.checked_add(max_len(hir)?)),
+        HirKind::Alternation(hirs) => {
+            hirs.iter().try_fold(0, |len, hir| Some(len.max(max_len(hir)?)))
+        }
+    }
+}
+
+/// Whether `pattern` may contain an assertion,
+/// like a word boundary or an anchor,
+/// whose outcome depends on the input around the match.
+///
+/// This is conservative: a `^` or `$` inside a character class
+/// is also considered an assertion.
+fn has_assertions(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' => {
+                if let Some('b' | 'B' | 'A' | 'z') = chars.next() {
+                    return true;
+                }
+            }
+            '^' | '$' => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __lexer_builder {
+    () => {
+        santiago::lexer::LexerBuilder::new()
+    };
+    ($context:ty) => {
+        santiago::lexer::LexerBuilder::<$context>::with_context()
+    };
 }
 
 #[doc(hidden)]
@@ -136,16 +398,27 @@ macro_rules! __lexer_rules_helper {
 
 /// Declarative utility for creating [LexerRules].
 ///
+/// The rules can start with `context Type;`
+/// to give their actions a [Lexer] with a context of that type,
+/// see [lex_with_context()](crate::lexer::lex_with_context()).
+///
+/// Panics if a pattern is not a valid regular expression,
+/// see [try_lexer_rules!](crate::try_lexer_rules) for a fallible alternative
+/// that also reports the rules that can never be selected.
+///
 /// Please read the [module documentation](crate) for more information and examples.
 #[macro_export]
 macro_rules! lexer_rules {
-    ($(
-        $( $states:literal )*
-        | $rule_name:literal
-        = $matcher:ident $matcher_arg:expr
-        $( => $action:expr )?
-    );* ;) => {{
-        let mut builder = santiago::lexer::LexerBuilder::new();
+    (
+        $( context $context:ty; )?
+        $(
+            $( $states:literal )*
+            | $rule_name:literal
+            = $matcher:ident $matcher_arg:expr
+            $( => $action:expr )?
+        );* ;
+    ) => {{
+        let mut builder = santiago::__lexer_builder!($( $context )?);
 
         $(santiago::__lexer_rules_helper!(
             builder
@@ -158,3 +431,38 @@ macro_rules! lexer_rules {
         builder.finish()
     }};
 }
+
+/// As [lexer_rules!](crate::lexer_rules)
+/// but returning all the [LexerBuilderError]s found
+/// instead of panicking.
+///
+/// The rules can start with `states "A" "B";`
+/// to declare the states that rule actions push,
+/// see [LexerBuilder::declare_states()].
+#[macro_export]
+macro_rules! try_lexer_rules {
+    (
+        $( context $context:ty; )?
+        $( states $( $declared_states:literal )+ ; )?
+        $(
+            $( $states:literal )*
+            | $rule_name:literal
+            = $matcher:ident $matcher_arg:expr
+            $( => $action:expr )?
+        );* ;
+    ) => {{
+        let mut builder = santiago::__lexer_builder!($( $context )?);
+
+        $(builder.declare_states(&[$( $declared_states ),+]);)?
+
+        $(santiago::__lexer_rules_helper!(
+            builder
+            $( $states )*
+            | $rule_name
+            = $matcher $matcher_arg
+            $( => $action )?
+        ));*;
+
+        builder.try_finish()
+    }};
+}
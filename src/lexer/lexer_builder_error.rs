@@ -0,0 +1,82 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+/// Internal representation of an error encountered
+/// by [LexerBuilder::try_finish()](crate::lexer::LexerBuilder::try_finish()).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LexerBuilderError {
+    /// A rule was defined with a pattern
+    /// that is not a valid regular expression.
+    InvalidPattern {
+        /// Name of the rule.
+        rule_name: String,
+        /// States in which the rule would have been active.
+        states:    Vec<String>,
+        /// The invalid pattern.
+        pattern:   String,
+        /// Human readable description of why the pattern is not valid.
+        message:   String,
+    },
+    /// A rule is active in a state that is neither `"DEFAULT"`
+    /// nor declared with
+    /// [LexerBuilder::declare_states()](crate::lexer::LexerBuilder::declare_states()),
+    /// so no rule action is known to push it.
+    UnreachableState {
+        /// Name of the rule.
+        rule_name: String,
+        /// State that is not declared.
+        state:     String,
+    },
+    /// Every match of a rule is also matched, at least as long,
+    /// by a rule declared before it in the same state,
+    /// so the rule can never be selected.
+    ShadowedRule {
+        /// Name of the rule that can never be selected.
+        rule_name:   String,
+        /// State in which the rule is shadowed.
+        state:       String,
+        /// Name of the rule declared before it that takes precedence.
+        shadowed_by: String,
+    },
+}
+
+impl std::fmt::Display for LexerBuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexerBuilderError::InvalidPattern {
+                rule_name,
+                states,
+                pattern,
+                message,
+            } => {
+                write!(
+                    f,
+                    "Error at lexer rule: {rule_name}\nIn states: \
+                     {states:?}\nThe pattern: {pattern}\nIs not a valid \
+                     regular expression: {message}"
+                )
+            }
+            LexerBuilderError::UnreachableState { rule_name, state } => {
+                write!(
+                    f,
+                    "Error at lexer rule: {rule_name}\nIn state: {state}\nThis \
+                     state is not declared, so the rule can never be active."
+                )
+            }
+            LexerBuilderError::ShadowedRule {
+                rule_name,
+                state,
+                shadowed_by,
+            } => {
+                write!(
+                    f,
+                    "Error at lexer rule: {rule_name}\nIn state: \
+                     {state}\nEvery match of this rule is also matched by the \
+                     rule: {shadowed_by}\nWhich is declared before, so this \
+                     rule can never be selected."
+                )
+            }
+        }
+    }
+}
@@ -5,7 +5,7 @@
 use crate::lexer::Position;
 
 /// Internal representation of an error encountered by the [Lexer](crate::lexer::Lexer).
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LexerError {
     /// Byte index relative to the [Lexer](crate::lexer::Lexer)
     /// input where the error was encountered.
@@ -11,9 +11,32 @@ use std::rc::Rc;
 /// [LexerRule] is exposed so you can use its type and traits,
 /// but normally you create [LexerRule]s
 /// by using a [LexerBuilder](crate::lexer::LexerBuilder).
-#[derive(Clone)]
-pub(crate) struct LexerRule {
-    pub(crate) action:  Rc<dyn Fn(&mut Lexer) -> NextLexeme>,
+#[allow(clippy::type_complexity)]
+pub(crate) struct LexerRule<Context> {
+    pub(crate) action:  Rc<dyn Fn(&mut Lexer<Context>) -> NextLexeme>,
     pub(crate) matcher: Rc<dyn Fn(&str) -> Option<usize>>,
+    /// Maximum length in bytes of the matches of this rule,
+    /// or none if they can be arbitrarily long.
+    pub(crate) max_len: Option<usize>,
     pub(crate) name:    String,
+    pub(crate) source:  LexerRuleSource,
+}
+
+impl<Context> Clone for LexerRule<Context> {
+    fn clone(&self) -> LexerRule<Context> {
+        LexerRule {
+            action:  self.action.clone(),
+            matcher: self.matcher.clone(),
+            max_len: self.max_len,
+            name:    self.name.clone(),
+            source:  self.source.clone(),
+        }
+    }
+}
+
+/// What a [LexerRule] was defined to match.
+#[derive(Clone)]
+pub(crate) enum LexerRuleSource {
+    Pattern(String),
+    String(String),
 }
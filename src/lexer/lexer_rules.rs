@@ -2,15 +2,95 @@
 //
 // SPDX-License-Identifier: GPL-3.0-only
 
+use crate::lexer::CompiledRules;
+use crate::lexer::Indentation;
 use crate::lexer::LexerRule;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 /// Group of lexer rules optimized for performance.
 ///
 /// [LexerRules] is exposed so you can use its type and traits,
 /// but normally you create [LexerRules]
 /// by using a [LexerBuilder](crate::lexer::LexerBuilder).
-#[derive(Clone)]
-pub struct LexerRules {
-    pub(crate) rules: HashMap<String, Vec<LexerRule>>,
+///
+/// The actions of the rules receive a [Lexer](crate::lexer::Lexer)
+/// that carries a user defined `Context`.
+pub struct LexerRules<Context = ()> {
+    pub(crate) rules:              HashMap<String, Vec<LexerRule<Context>>>,
+    /// Automata for the rules of each state,
+    /// or empty if these [LexerRules] were not compiled.
+    pub(crate) compiled:           HashMap<String, Rc<CompiledRules>>,
+    pub(crate) indentation:        Option<Indentation>,
+    /// States where the [Indentation] is enabled.
+    pub(crate) indentation_states: Vec<String>,
+}
+
+impl<Context> Clone for LexerRules<Context> {
+    fn clone(&self) -> LexerRules<Context> {
+        LexerRules {
+            rules:              self.rules.clone(),
+            compiled:           self.compiled.clone(),
+            indentation:        self.indentation.clone(),
+            indentation_states: self.indentation_states.clone(),
+        }
+    }
+}
+
+impl<Context> LexerRules<Context> {
+    /// Return a copy of these [LexerRules]
+    /// where the rules of each state are indexed,
+    /// so that the [Lexer](crate::lexer::Lexer) does not try them one by one.
+    ///
+    /// `string` rules are merged into a prefix tree
+    /// that finds all of their matches in a single pass.
+    /// `pattern` rules are merged into a set of regular expressions
+    /// that tells which of them match in a single pass,
+    /// and then only those run their own regular expression
+    /// to compute the length of their match.
+    ///
+    /// `pattern` rules are not merged into a single automaton
+    /// that also reports the length of the matches,
+    /// because such an automaton finds the longest match of every pattern,
+    /// while a regular expression may prefer a shorter one,
+    /// like `/\*(.|\n)*?\*/` or `a|ab`.
+    ///
+    /// The [Lexer](crate::lexer::Lexer) produces the same
+    /// [Lexemes](crate::lexer::Lexeme),
+    /// taking the longest match and the first rule declared among them,
+    /// but it is usually faster on lexers with many `string` rules,
+    /// or with many `pattern` rules of which only a few match at a time.
+    pub fn compile(&self) -> LexerRules<Context> {
+        LexerRules {
+            rules:              self.rules.clone(),
+            compiled:           self
+                .rules
+                .iter()
+                .map(|(state, rules)| {
+                    (state.clone(), Rc::new(CompiledRules::new(rules)))
+                })
+                .collect(),
+            indentation:        self.indentation.clone(),
+            indentation_states: self.indentation_states.clone(),
+        }
+    }
+
+    /// Number of bytes past the end of a [Lexeme](crate::lexer::Lexeme)
+    /// that the [Lexer](crate::lexer::Lexer) may look at to produce it.
+    ///
+    /// The rules whose matches can be arbitrarily long
+    /// are assumed to look at most one character past their match,
+    /// which holds unless their pattern backtracks,
+    /// like the `b*c` in `a(b*c)?`.
+    pub(crate) fn lookahead(&self) -> usize {
+        let rules =
+            self.rules.values().flatten().filter_map(|rule| rule.max_len);
+        let comments = self.indentation.iter().flat_map(|indentation| {
+            indentation.comments.iter().map(String::len)
+        });
+
+        // A character, like the one an assertion like `\b` or `$` looks at,
+        // is at most 4 bytes long.
+        rules.chain(comments).max().unwrap_or(0) + 4
+    }
 }
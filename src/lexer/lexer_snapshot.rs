@@ -0,0 +1,94 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::IndentationState;
+use crate::lexer::Lexer;
+use crate::lexer::LexerRules;
+use crate::lexer::Position;
+use std::collections::LinkedList;
+use std::collections::VecDeque;
+
+/// State of a [Lexer] between two [Lexeme](crate::lexer::Lexeme)s,
+/// which determines how it lexes the input after them,
+/// and from where it can resume lexing.
+#[derive(Clone)]
+pub(crate) struct LexerSnapshot {
+    pub(crate) position: Position,
+    states_stack:        Vec<String>,
+    indentation:         IndentationState,
+}
+
+impl LexerSnapshot {
+    /// Whether a [Lexer] in the state of this [LexerSnapshot]
+    /// behaves as one in the state of `other`,
+    /// given the same input after them.
+    pub(crate) fn same_state(&self, other: &LexerSnapshot) -> bool {
+        self.states_stack == other.states_stack
+            && self.indentation == other.indentation
+    }
+
+    /// Move this [LexerSnapshot] with the given function,
+    /// for instance to keep it in sync with an edited input.
+    pub(crate) fn map_position(
+        &self,
+        function: impl Fn(&Position) -> Position,
+    ) -> LexerSnapshot {
+        LexerSnapshot {
+            position:     function(&self.position),
+            states_stack: self.states_stack.clone(),
+            indentation:  self.indentation.clone(),
+        }
+    }
+}
+
+impl<'a> Lexer<'a> {
+    /// Create a [Lexer] for the given input
+    /// that continues from where the `snapshot` was taken.
+    pub(crate) fn resume(
+        rules: &'a LexerRules,
+        input: &'a str,
+        snapshot: &'a LexerSnapshot,
+    ) -> Lexer<'a> {
+        Lexer {
+            rules,
+            input,
+            current_byte_index: snapshot.position.byte_index,
+            current_match_len: 0,
+            current_rule_name: "",
+            position: snapshot.position.clone(),
+            states_stack: snapshot
+                .states_stack
+                .iter()
+                .map(String::as_str)
+                .collect::<LinkedList<&str>>(),
+            finished: false,
+            pending: VecDeque::new(),
+            trivia: Vec::new(),
+            indentation: snapshot.indentation.clone(),
+            expected_kinds: None,
+            context: (),
+        }
+    }
+}
+
+impl<'a, Context> Lexer<'a, Context> {
+    /// State of this [Lexer],
+    /// or none if some [Lexeme](crate::lexer::Lexeme)s
+    /// or [Trivia](crate::lexer::Trivia) are still pending.
+    pub(crate) fn snapshot(&self) -> Option<LexerSnapshot> {
+        if !self.pending.is_empty() || !self.trivia.is_empty() {
+            return None;
+        }
+
+        Some(LexerSnapshot {
+            position:     self.position.clone(),
+            states_stack: self
+                .states_stack
+                .iter()
+                .map(|state| state.to_string())
+                .collect(),
+            indentation:  self.indentation.clone(),
+        })
+    }
+}
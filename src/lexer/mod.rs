@@ -6,39 +6,74 @@
 //! into groups of characters with related meaning.
 //!
 //! Please read the [crate documentation](crate) for more information and examples.
+mod compiled_rules;
+mod indentation;
 mod lexeme;
+mod lexeme_lattice;
 mod lexer_builder;
+mod lexer_builder_error;
 mod lexer_error;
 mod lexer_rule;
 mod lexer_rules;
+mod lexer_snapshot;
 mod position;
+mod trivia;
 
+pub(crate) use compiled_rules::CompiledRules;
+pub use indentation::Indentation;
+pub(crate) use indentation::IndentationState;
+pub use indentation::Tabs;
 pub use lexeme::Lexeme;
+pub use lexeme_lattice::lex_lattice;
+pub use lexeme_lattice::LexemeLattice;
 pub use lexer_builder::LexerBuilder;
+pub use lexer_builder_error::LexerBuilderError;
 pub use lexer_error::LexerError;
 pub(crate) use lexer_rule::LexerRule;
+pub(crate) use lexer_rule::LexerRuleSource;
 pub use lexer_rules::LexerRules;
+pub(crate) use lexer_snapshot::LexerSnapshot;
 pub use position::Position;
+use std::collections::HashSet;
 use std::collections::LinkedList;
+use std::collections::VecDeque;
 use std::rc::Rc;
+pub use trivia::Trivia;
 
 /// Core implementation of the algorithm.
 ///
-/// [Lexer] is exposed so you can use its type and traits,
-/// but normally you just use [lex()].
+/// [Lexer] is an iterator that produces the [Lexeme]s of the input on demand,
+/// which is useful for lexing big inputs lazily or stopping early.
+/// It stops after the first error.
+/// Normally you just use [lex()] to get all the [Lexeme]s at once.
 ///
 /// Please read the [crate documentation](crate) for more information and examples.
-pub struct Lexer<'a> {
-    input:                 &'a str,
-    current_byte_index:    usize,
+pub struct Lexer<'a, Context = ()> {
+    rules:                     &'a LexerRules<Context>,
+    input:                     &'a str,
+    current_byte_index:        usize,
     /// The current match length.
     ///
     /// You can use this field to go back a few bytes in the input
     /// (for instance to simulate a "trailing context" pattern in Flex).
-    pub current_match_len: usize,
-    current_rule_name:     &'a str,
-    position:              Position,
-    states_stack:          LinkedList<&'a str>,
+    pub current_match_len:     usize,
+    current_rule_name:         &'a str,
+    position:                  Position,
+    states_stack:              LinkedList<&'a str>,
+    finished:                  bool,
+    /// [Lexeme]s returned by an action that were not produced yet.
+    pending:                   VecDeque<Lexeme>,
+    /// [Trivia] skipped since the last [Lexeme] was produced.
+    trivia:                    Vec<Trivia>,
+    indentation:               IndentationState,
+    /// Kinds of the [Lexeme]s that the parser expects next,
+    /// whose rules are tried first.
+    pub(crate) expected_kinds: Option<HashSet<String>>,
+    /// User defined value that the rule actions can read and modify,
+    /// for instance to keep track of nesting levels or a symbol table.
+    ///
+    /// See [lex_with_context()].
+    pub context:               Context,
 }
 
 /// Return type of a lexer rule action.
@@ -52,70 +87,150 @@ pub enum NextLexeme {
         /// Raw contents of the [Lexeme].
         raw:  String,
     },
+    /// We returned several [Lexeme]s at once,
+    /// which are produced in order.
+    Lexemes(Vec<Lexeme>),
     /// Instructs the [Lexer] that the current [Lexeme] has been skipped.
     Skip,
     /// Instructs the [Lexer] that we reached the end of the input.
     Finished,
 }
 
+impl<'a, Context> Iterator for Lexer<'a, Context> {
+    type Item = Result<Lexeme, LexerError>;
+
+    fn next(&mut self) -> Option<Result<Lexeme, LexerError>> {
+        while !self.finished {
+            if let Some(lexeme) = self.pending.pop_front() {
+                return Some(Ok(self.attach_trivia(lexeme)));
+            }
+
+            let position = self.position.clone();
+            let state = *self.states_stack.back().unwrap();
+            let next_lexeme = match self.indent() {
+                Some(next_lexeme) => next_lexeme,
+                None => self.next_lexeme(),
+            };
+
+            match next_lexeme {
+                NextLexeme::Error(error) => {
+                    self.finished = true;
+                    return Some(Err(error));
+                }
+                NextLexeme::Lexeme { kind, raw } => {
+                    let lexeme = self.lexeme(position, kind, raw);
+                    self.track_indentation(&lexeme, state);
+
+                    return Some(Ok(self.attach_trivia(lexeme)));
+                }
+                NextLexeme::Lexemes(lexemes) => {
+                    for lexeme in &lexemes {
+                        self.track_indentation(lexeme, state);
+                    }
+                    self.pending.extend(lexemes);
+                }
+                NextLexeme::Skip => {}
+                NextLexeme::Finished => {
+                    if !self.dedent_at_end() {
+                        self.finished = true;
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
 impl<'a> Lexer<'a> {
-    fn next_lexeme(&mut self, rules: &'a LexerRules) -> NextLexeme {
+    /// Create a [Lexer] for the given input according to the provided rules.
+    pub fn new(rules: &'a LexerRules, input: &'a str) -> Lexer<'a> {
+        Lexer::with_context(rules, input, ())
+    }
+}
+
+impl<'a, Context> Lexer<'a, Context> {
+    /// Create a [Lexer] for the given input according to the provided rules,
+    /// starting with the given `context`.
+    pub fn with_context(
+        rules: &'a LexerRules<Context>,
+        input: &'a str,
+        context: Context,
+    ) -> Lexer<'a, Context> {
+        let mut lexer = Lexer {
+            rules,
+            input,
+            current_byte_index: 0,
+            current_match_len: 0,
+            current_rule_name: "",
+            position: Position { column: 1, line: 1, byte_index: 0 },
+            states_stack: LinkedList::new(),
+            finished: false,
+            pending: VecDeque::new(),
+            trivia: Vec::new(),
+            indentation: IndentationState::new(),
+            expected_kinds: None,
+            context,
+        };
+
+        lexer.push_state("DEFAULT");
+
+        lexer
+    }
+
+    fn next_lexeme(&mut self) -> NextLexeme {
+        let rules = self.rules;
         let state = self.states_stack.back().unwrap();
         let input_len = self.input.len();
 
         if self.current_byte_index < input_len
             || (self.current_byte_index == input_len && *state != "DEFAULT")
         {
-            let mut matches_: LinkedList<(usize, usize)> = LinkedList::new();
             let input = &self.input[self.current_byte_index..];
             let active_rules = match rules.rules.get(*state) {
                 Some(rules) => rules.as_slice(),
                 None => &[],
             };
 
-            for (rule_index, rule) in active_rules.iter().enumerate() {
-                let matcher = &rule.matcher;
-
-                if let Some(len) = matcher(input) {
-                    matches_.push_back((len, rule_index));
+            let expected_match = match &self.expected_kinds {
+                Some(expected_kinds) => {
+                    longest_expected_match(active_rules, input, expected_kinds)
                 }
-            }
-
-            if matches_.is_empty() {
-                let active_rule_names: Vec<String> = active_rules
-                    .iter()
-                    .map(|rule| format!("{:?}", rule.name))
-                    .collect();
-
-                return NextLexeme::Error(LexerError {
-                    byte_index:   self.current_byte_index,
-                    match_len:    None,
-                    message:      format!(
-                        "Expecting one of the following {} lexemes: {}",
-                        active_rules.len(),
-                        active_rule_names.join(", ")
-                    ),
-                    position:     self.position.clone(),
-                    states_stack: self
-                        .states_stack
-                        .iter()
-                        .map(|state| state.to_string())
-                        .collect(),
+                None => None,
+            };
+            let longest_match =
+                expected_match.or_else(|| match rules.compiled.get(*state) {
+                    Some(compiled) => {
+                        compiled.longest_match(active_rules, input)
+                    }
+                    None => longest_match(active_rules, input),
                 });
-            }
 
-            // Pick matches with the same maximum length
-            // and take the first rule declared.
-            let max_len = matches_
-                .iter()
-                .max_by(|left, right| left.0.cmp(&right.0))
-                .unwrap()
-                .0;
-            let (len, rule_index): (usize, usize) = matches_
-                .into_iter()
-                .filter(|match_| match_.0 == max_len)
-                .min_by(|left, right| left.1.cmp(&right.1))
-                .unwrap();
+            let (len, rule_index) = match longest_match {
+                Some(longest_match) => longest_match,
+                None => {
+                    let active_rule_names: Vec<String> = active_rules
+                        .iter()
+                        .map(|rule| format!("{:?}", rule.name))
+                        .collect();
+
+                    return NextLexeme::Error(LexerError {
+                        byte_index:   self.current_byte_index,
+                        match_len:    None,
+                        message:      format!(
+                            "Expecting one of the following {} lexemes: {}",
+                            active_rules.len(),
+                            active_rule_names.join(", ")
+                        ),
+                        position:     self.position.clone(),
+                        states_stack: self
+                            .states_stack
+                            .iter()
+                            .map(|state| state.to_string())
+                            .collect(),
+                    });
+                }
+            };
 
             self.current_match_len = len;
             self.current_rule_name = &active_rules[rule_index].name;
@@ -126,6 +241,99 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    /// Length of the input from the current position
+    /// up to the next position where any of the active rules match,
+    /// or up to the end of the input.
+    fn unmatched_len(&self) -> usize {
+        let state = self.states_stack.back().unwrap();
+        let input = &self.input[self.current_byte_index..];
+        let active_rules = match self.rules.rules.get(*state) {
+            Some(rules) => rules.as_slice(),
+            None => &[],
+        };
+
+        input
+            .char_indices()
+            .skip(1)
+            .map(|(byte_index, _)| byte_index)
+            .find(|byte_index| {
+                let input = &input[*byte_index..];
+
+                active_rules.iter().any(|rule| (rule.matcher)(input).is_some())
+            })
+            .unwrap_or(input.len())
+    }
+
+    /// Skip the part of the input that caused the `error`,
+    /// returning it as a [Lexeme] of kind `"ERROR"`
+    /// so that the [Lexer] can continue after it,
+    /// or none if there is nothing left to skip.
+    fn recover(&mut self, error: &LexerError) -> Option<Lexeme> {
+        let len = match error.match_len {
+            Some(len) if len > 0 => len,
+            _ => self.unmatched_len(),
+        };
+
+        // There is nothing left to skip at the end of the input.
+        if len == 0 {
+            return None;
+        }
+
+        let position = self.position.clone();
+        self.current_match_len = len;
+        self.current_rule_name = "ERROR";
+        self.finished = false;
+
+        match self.take() {
+            NextLexeme::Lexeme { kind, raw } => {
+                Some(self.lexeme(position, kind, raw))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// [Lexeme] of the given `kind` and `raw` contents,
+    /// starting at `position` and spanning the input consumed since then,
+    /// which is nothing if the match is going to be retried.
+    fn lexeme(&self, position: Position, kind: String, raw: String) -> Lexeme {
+        Lexeme {
+            kind,
+            raw,
+            position,
+            position_end: self.position.clone(),
+            leading_trivia: Vec::new(),
+            trailing_trivia: Vec::new(),
+        }
+    }
+
+    /// Attach the [Trivia] skipped so far to the given [Lexeme],
+    /// which comes right after it.
+    fn attach_trivia(&mut self, mut lexeme: Lexeme) -> Lexeme {
+        lexeme.leading_trivia.splice(0..0, self.trivia.drain(..));
+        lexeme
+    }
+
+    /// Attach the [Trivia] skipped at the end of the input
+    /// to the last of the given [Lexeme]s.
+    pub(crate) fn attach_trailing_trivia(
+        &mut self,
+        lexemes: &mut [Rc<Lexeme>],
+    ) {
+        if let Some(lexeme) = lexemes.last_mut() {
+            Rc::make_mut(lexeme).trailing_trivia.append(&mut self.trivia);
+        }
+    }
+
+    /// [Trivia] skipped since the last [Lexeme] was produced.
+    ///
+    /// Once the [Lexer] is exhausted,
+    /// this is the [Trivia] at the end of the input,
+    /// which [lex()] attaches to the last [Lexeme]
+    /// as its [Lexeme::trailing_trivia].
+    pub fn pending_trivia(&self) -> &[Trivia] {
+        &self.trivia
+    }
+
     /// Return the current match contents.
     pub fn matched(&self) -> &str {
         &self.input[self.current_byte_index..][..self.current_match_len]
@@ -139,7 +347,10 @@ impl<'a> Lexer<'a> {
 
     /// As [Lexer::take()]
     /// but applying `function` over [Lexer::matched()] first.
-    pub fn take_and_map(&mut self, function: fn(&str) -> String) -> NextLexeme {
+    pub fn take_and_map(
+        &mut self,
+        function: impl Fn(&str) -> String,
+    ) -> NextLexeme {
         let matched = self.matched().to_string();
         self.position.consume(&matched);
         self.current_byte_index += self.current_match_len;
@@ -150,16 +361,104 @@ impl<'a> Lexer<'a> {
         NextLexeme::Lexeme { kind, raw }
     }
 
+    /// Instructs the [Lexer] that we want to include [Lexer::matched()]
+    /// in the final [Lexeme]s,
+    /// split into consecutive [Lexeme]s
+    /// with the given kinds and lengths in bytes.
+    ///
+    /// For instance, `>>` can be split into two `>`
+    /// with `lexer.take_split(&[(">", 1), (">", 1)])`.
+    ///
+    /// Signals an error if the lengths do not add up to the current match.
+    pub fn take_split(&mut self, parts: &[(&str, usize)]) -> NextLexeme {
+        let matched = self.matched().to_string();
+        let mut raws = Vec::with_capacity(parts.len());
+        let mut start = 0;
+
+        for (_, len) in parts {
+            match matched.get(start..start + len) {
+                Some(raw) => raws.push(raw),
+                None => break,
+            }
+            start += len;
+        }
+
+        if raws.len() != parts.len() || start != matched.len() {
+            return self.error(&format!(
+                "Cannot split a match of {} bytes into parts of {:?} bytes",
+                matched.len(),
+                parts.iter().map(|part| part.1).collect::<Vec<usize>>(),
+            ));
+        }
+
+        let lexemes = parts
+            .iter()
+            .zip(raws)
+            .map(|((kind, _), raw)| {
+                let position = self.position.clone();
+                self.position.consume(raw);
+
+                Lexeme {
+                    kind: kind.to_string(),
+                    raw: raw.to_string(),
+                    position,
+                    position_end: self.position.clone(),
+                    leading_trivia: Vec::new(),
+                    trailing_trivia: Vec::new(),
+                }
+            })
+            .collect();
+        self.current_byte_index += self.current_match_len;
+
+        NextLexeme::Lexemes(lexemes)
+    }
+
+    /// Return a [Lexeme] of the given `kind` with empty contents
+    /// at the current position of the input,
+    /// without consuming the input.
+    ///
+    /// This is useful for injecting synthetic [Lexeme]s
+    /// with [NextLexeme::Lexemes],
+    /// like the `DEDENT`s that close several indentation levels at once.
+    pub fn synthetic(&self, kind: &str) -> Lexeme {
+        Lexeme {
+            kind:            kind.to_string(),
+            raw:             String::new(),
+            position:        self.position.clone(),
+            position_end:    self.position.clone(),
+            leading_trivia:  Vec::new(),
+            trailing_trivia: Vec::new(),
+        }
+    }
+
     /// Instructs the [Lexer] that we don't want to include [Lexer::matched()]
     /// in the final [Lexeme]s.
+    ///
+    /// The match is kept as [Trivia] of the next [Lexeme].
     pub fn skip(&mut self) -> NextLexeme {
         let matched = self.matched().to_string();
-        self.position.consume(&matched);
-        self.current_byte_index += self.current_match_len;
+
+        if !matched.is_empty() {
+            let position = self.position.clone();
+            self.advance();
+            self.trivia.push(Trivia {
+                kind: self.current_rule_name.to_string(),
+                raw: matched,
+                position,
+                position_end: self.position.clone(),
+            });
+        }
 
         NextLexeme::Skip
     }
 
+    /// Move past the current match.
+    fn advance(&mut self) {
+        let matched = self.matched().to_string();
+        self.position.consume(&matched);
+        self.current_byte_index += self.current_match_len;
+    }
+
     /// Instructs the [Lexer] that we want to include [Lexer::matched()]
     /// in the final [Lexeme]s
     /// and that we want the current input position
@@ -175,7 +474,7 @@ impl<'a> Lexer<'a> {
     /// but applying `function` over [Lexer::matched()] first.
     pub fn take_and_map_and_retry(
         &mut self,
-        function: fn(&str) -> String,
+        function: impl Fn(&str) -> String,
     ) -> NextLexeme {
         let matched = self.matched().to_string();
 
@@ -227,40 +526,152 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// Length of the longest match at the start of the `input`
+/// and the index of the first rule that produces it,
+/// or none if none of the `rules` match.
+fn longest_match<Context>(
+    rules: &[LexerRule<Context>],
+    input: &str,
+) -> Option<(usize, usize)> {
+    let mut matches_: LinkedList<(usize, usize)> = LinkedList::new();
+
+    for (rule_index, rule) in rules.iter().enumerate() {
+        let matcher = &rule.matcher;
+
+        if let Some(len) = matcher(input) {
+            matches_.push_back((len, rule_index));
+        }
+    }
+
+    // Pick matches with the same maximum length
+    // and take the first rule declared.
+    let max_len = matches_.iter().max_by(|left, right| left.0.cmp(&right.0))?.0;
+
+    matches_
+        .into_iter()
+        .filter(|match_| match_.0 == max_len)
+        .min_by(|left, right| left.1.cmp(&right.1))
+}
+
+/// Length of the longest match at the start of the `input`
+/// and the index of the first rule that produces it,
+/// considering only the `rules` whose name is in `expected_kinds`,
+/// or none if none of them match.
+fn longest_expected_match<Context>(
+    rules: &[LexerRule<Context>],
+    input: &str,
+    expected_kinds: &HashSet<String>,
+) -> Option<(usize, usize)> {
+    rules
+        .iter()
+        .enumerate()
+        .filter(|(_, rule)| expected_kinds.contains(&rule.name))
+        .filter_map(|(rule_index, rule)| {
+            (rule.matcher)(input).map(|len| (len, rule_index))
+        })
+        .min_by(|left, right| right.0.cmp(&left.0).then(left.1.cmp(&right.1)))
+}
+
 /// Perform lexical analysis of the given input according to the provided rules.
+///
+/// The skipped parts of the input are kept as [Trivia] of the [Lexeme]s,
+/// so that the input can be reconstructed from them,
+/// see [Lexeme::text_with_trivia()].
 pub fn lex(
     rules: &LexerRules,
     input: &str,
 ) -> Result<Vec<Rc<Lexeme>>, LexerError> {
-    let mut lexer = Lexer {
-        input,
-        current_byte_index: 0,
-        current_match_len: 0,
-        current_rule_name: "",
-        position: Position { column: 1, line: 1 },
-        states_stack: LinkedList::new(),
-    };
+    let mut lexer = Lexer::new(rules, input);
+    let mut lexemes = Vec::new();
+
+    for lexeme in &mut lexer {
+        lexemes.push(Rc::new(lexeme?));
+    }
+    lexer.attach_trailing_trivia(&mut lexemes);
+
+    Ok(lexemes)
+}
+
+/// Split the given input into one [Lexeme] per character,
+/// whose kind and raw value are the character itself.
+///
+/// This is the input of scannerless grammars,
+/// see [GrammarBuilder::rule_to_chars()](crate::grammar::GrammarBuilder::rule_to_chars()).
+pub fn lex_chars(input: &str) -> Vec<Rc<Lexeme>> {
+    let mut position = Position { column: 1, line: 1, byte_index: 0 };
+
+    input
+        .chars()
+        .map(|char| {
+            let raw = char.to_string();
+            let start = position.clone();
+            position.consume(&raw);
+
+            Rc::new(Lexeme {
+                kind: raw.clone(),
+                raw,
+                position: start,
+                position_end: position.clone(),
+                leading_trivia: Vec::new(),
+                trailing_trivia: Vec::new(),
+            })
+        })
+        .collect()
+}
 
-    lexer.push_state("DEFAULT");
+/// Perform lexical analysis of the given input according to the provided rules,
+/// starting with the given `context`.
+///
+/// The rule actions can read and modify the context
+/// through [Lexer::context],
+/// and its final value is returned together with the [Lexeme]s.
+pub fn lex_with_context<Context>(
+    rules: &LexerRules<Context>,
+    input: &str,
+    context: Context,
+) -> Result<(Vec<Rc<Lexeme>>, Context), LexerError> {
+    let mut lexer = Lexer::with_context(rules, input, context);
+    let mut lexemes = Vec::new();
 
-    let mut lexemes = LinkedList::new();
+    for lexeme in &mut lexer {
+        lexemes.push(Rc::new(lexeme?));
+    }
+    lexer.attach_trailing_trivia(&mut lexemes);
 
-    loop {
-        let position = lexer.position.clone();
+    Ok((lexemes, lexer.context))
+}
 
-        match lexer.next_lexeme(rules) {
-            NextLexeme::Error(error) => {
-                return Err(error);
-            }
-            NextLexeme::Lexeme { kind, raw } => {
-                lexemes.push_back(Rc::new(Lexeme { kind, position, raw }))
-            }
-            NextLexeme::Skip => {}
-            NextLexeme::Finished => {
-                break;
+/// Perform lexical analysis of the given input according to the provided rules,
+/// recovering from the errors found in the input.
+///
+/// Every time the [Lexer] finds an error,
+/// the part of the input that caused it
+/// is returned as a [Lexeme] of kind `"ERROR"`,
+/// and the [Lexer] continues right after it.
+/// If no rule matched the input,
+/// this part extends up to the next position where any of the active rules match.
+///
+/// Return all the [Lexeme]s together with the errors that were found.
+pub fn lex_with_recovery(
+    rules: &LexerRules,
+    input: &str,
+) -> (Vec<Rc<Lexeme>>, Vec<LexerError>) {
+    let mut lexer = Lexer::new(rules, input);
+    let mut lexemes = Vec::new();
+    let mut errors = Vec::new();
+
+    while let Some(lexeme) = lexer.next() {
+        match lexeme {
+            Ok(lexeme) => lexemes.push(Rc::new(lexeme)),
+            Err(error) => {
+                if let Some(lexeme) = lexer.recover(&error) {
+                    lexemes.push(Rc::new(lexer.attach_trivia(lexeme)));
+                }
+                errors.push(error);
             }
         }
     }
+    lexer.attach_trailing_trivia(&mut lexemes);
 
-    Ok(lexemes.into_iter().collect())
+    (lexemes, errors)
 }
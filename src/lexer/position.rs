@@ -11,26 +11,30 @@
 #[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Position {
     /// Line number.
-    pub line:   usize,
+    pub line:       usize,
     /// Column number.
-    pub column: usize,
+    pub column:     usize,
+    /// Byte index relative to the input.
+    pub byte_index: usize,
 }
 
 impl std::fmt::Debug for Position {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}, {})", self.line, self.column)
+        write!(f, "({}, {}, byte {})", self.line, self.column, self.byte_index)
     }
 }
 
 impl std::fmt::Display for Position {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        write!(f, "({}, {})", self.line, self.column)
     }
 }
 
 impl Position {
     /// Iterates the `input` updating the [Position] accordingly.
     pub fn consume(&mut self, input: &str) {
+        self.byte_index += input.len();
+
         for char in input.chars() {
             if char == '\n' {
                 self.line += 1;
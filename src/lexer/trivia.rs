@@ -0,0 +1,44 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::Position;
+use std::ops::Range;
+
+/// Part of the input that the [Lexer](crate::lexer::Lexer) skipped,
+/// like whitespace or comments.
+///
+/// [Trivia] is attached to the [Lexeme](crate::lexer::Lexeme)s around it,
+/// see [Lexeme::leading_trivia](crate::lexer::Lexeme::leading_trivia)
+/// and [Lexeme::trailing_trivia](crate::lexer::Lexeme::trailing_trivia).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Trivia {
+    /// Name of the [LexerRules](crate::lexer::LexerRules)
+    /// that skipped this [Trivia],
+    /// or the kind of the [Indentation](crate::lexer::Indentation) lexemes
+    /// for the newlines and indentation that it skipped.
+    pub kind:         String,
+    /// Raw content of this [Trivia], exactly as in the input.
+    pub raw:          String,
+    /// [Position] where this [Trivia] starts, relative to the input
+    /// passed to the [Lexer](crate::lexer::Lexer).
+    pub position:     Position,
+    /// [Position] where this [Trivia] ends (exclusive), relative to the input
+    /// passed to the [Lexer](crate::lexer::Lexer).
+    pub position_end: Position,
+}
+
+impl Trivia {
+    /// Range of bytes of the input
+    /// passed to the [Lexer](crate::lexer::Lexer)
+    /// that produced this [Trivia].
+    pub fn byte_range(&self) -> Range<usize> {
+        self.position.byte_index..self.position_end.byte_index
+    }
+}
+
+impl std::fmt::Display for Trivia {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:?} {}", self.kind, self.raw, self.position)
+    }
+}
@@ -90,7 +90,7 @@
 //! A [Lexeme](lexer::Lexeme) gives us information like:
 //! - Token kind
 //! - Contents
-//! - Position (line and column number)
+//! - Position (line, column and byte index) where it starts and ends
 //!
 //! In this case we have two kinds of tokens, an `INT` and a `PLUS`:
 //! ```text
@@ -279,9 +279,32 @@
 //!   [skip_and_retry](lexer::Lexer::skip_and_retry()),
 //!   the current match,
 //!   or signal an [error](lexer::Lexer::error()).
+//! - Actions can also produce several [Lexeme](lexer::Lexeme)s at once
+//!   with [NextLexeme::Lexemes](lexer::NextLexeme::Lexemes),
+//!   for instance by splitting the current match with
+//!   [take_split](lexer::Lexer::take_split())
+//!   or by injecting [synthetic](lexer::Lexer::synthetic()) ones.
+//!
+//! Rule actions are closures,
+//! so they can capture any configuration they need,
+//! like a table of reserved words.
+//! They can also keep track of state while lexing
+//! in a user defined [context](lexer::Lexer::context),
+//! see [lex_with_context()](lexer::lex_with_context()).
 //!
 //! For convenience, the stack of states is initially populated with `"DEFAULT"`.
 //!
+//! Skipped matches, like whitespace or comments, are not lost:
+//! they are kept as [Trivia](lexer::Trivia) of the next Lexeme,
+//! or of the last one at the end of the input,
+//! so that the lossless view of a Parse Tree, its [Cst](parser::Cst),
+//! prints back the input byte by byte.
+//!
+//! Indentation-sensitive languages, like Python or YAML,
+//! can let the Lexer track the indentation of each line
+//! and emit `INDENT`, `DEDENT` and `NEWLINE` lexemes,
+//! see [LexerBuilder::indentation()](lexer::LexerBuilder::indentation()).
+//!
 //! ## Grammars
 //!
 //! A [Grammar](https://en.wikipedia.org/wiki/Formal_grammar)
@@ -302,6 +325,20 @@
 //! - `"given_name" => lexemes "Kevin"`
 //! - `"given_name" => lexemes "..."`
 //!
+//! Grammars can also be written directly over characters,
+//! without a separate lexer,
+//! using character classes like `"digit" => chars "[0-9]"`,
+//! literal strings like `"if" => string "if"`,
+//! and restrictions that implement the longest match
+//! of identifiers and keywords,
+//! like `"id" => not_followed_by "[a-z]"` and `"id" => reject "if"`,
+//! see [parse_str()](parser::parse_str()).
+//!
+//! Editors and language servers that parse the same input after every
+//! keystroke can keep it in a [Document](parser::Document),
+//! which relexes and reparses only the region around each edit
+//! and reuses the Lexemes and parser state before and after it.
+//!
 //! # Examples
 //!
 //! In this section we explore a few more full examples,
@@ -575,6 +612,7 @@
 //! - <https://github.com/kamadorueda/santiago>
 //!
 //! Cheers ❤️
+pub mod diagnostics;
 pub mod grammar;
 pub mod lexer;
 pub mod parser;
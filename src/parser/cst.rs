@@ -0,0 +1,102 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::Lexeme;
+use crate::parser::Tree;
+
+/// Lossless view of a [Tree], also known as a Concrete Syntax Tree,
+/// that keeps the [Trivia](crate::lexer::Trivia) of its [Lexeme]s.
+///
+/// The text of a [Cst] is exactly the input that produced it,
+/// which makes it suitable for formatters and refactoring tools
+/// that need to keep the comments.
+///
+/// The only exception are the [Lexeme]s whose raw contents
+/// were changed by the rule actions of the [Lexer](crate::lexer::Lexer)
+/// with [take_and_map](crate::lexer::Lexer::take_and_map()),
+/// which contribute their new contents instead.
+/// The [Lexeme]s that are retried,
+/// with [take_and_retry](crate::lexer::Lexer::take_and_retry())
+/// for instance,
+/// cover no input since it is matched again by the next [Lexeme]s,
+/// so they only contribute their [Trivia](crate::lexer::Trivia).
+///
+/// Normally you create a [Cst] by using [Tree::cst()].
+#[derive(Debug)]
+pub enum Cst<'a> {
+    /// A [Lexeme] together with its [Trivia](crate::lexer::Trivia).
+    Lexeme(&'a Lexeme),
+    /// Group of many [Cst]s.
+    Node {
+        /// Name of the [GrammarRule](crate::grammar::GrammarRule) that produced this node.
+        rule_name: &'a str,
+        /// Children of this Node.
+        children:  Vec<Cst<'a>>,
+    },
+    /// Part of the input that could not be parsed, see [Tree::Error].
+    Error {
+        /// Human readable description of the error.
+        message:  &'a str,
+        /// Skipped [Lexeme]s.
+        children: Vec<Cst<'a>>,
+    },
+}
+
+impl<'a> Cst<'a> {
+    /// Create a [Cst] that views the given [Tree].
+    pub fn new<AST>(tree: &'a Tree<AST>) -> Cst<'a> {
+        match tree {
+            Tree::Leaf(lexeme) => Cst::Lexeme(lexeme),
+            Tree::Node { rule_name, leaves, .. } => Cst::Node {
+                rule_name: rule_name.as_str(),
+                children:  leaves.iter().map(|leaf| Cst::new(leaf)).collect(),
+            },
+            Tree::Error { lexemes, message, .. } => Cst::Error {
+                message:  message.as_str(),
+                children: lexemes
+                    .iter()
+                    .map(|lexeme| Cst::Lexeme(lexeme))
+                    .collect(),
+            },
+        }
+    }
+
+    /// [Lexeme]s of this [Cst], in order.
+    pub fn lexemes(&self) -> Vec<&'a Lexeme> {
+        match self {
+            Cst::Lexeme(lexeme) => vec![lexeme],
+            Cst::Node { children, .. } | Cst::Error { children, .. } => {
+                children.iter().flat_map(Cst::lexemes).collect()
+            }
+        }
+    }
+
+    /// Text of this [Cst],
+    /// which is the concatenation of the [Lexeme]s and their
+    /// [Trivia](crate::lexer::Trivia),
+    /// skipping the raw contents of the [Lexeme]s that cover no input.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+
+        for lexeme in self.lexemes() {
+            for trivia in &lexeme.leading_trivia {
+                text.push_str(&trivia.raw);
+            }
+            if !lexeme.byte_range().is_empty() {
+                text.push_str(&lexeme.raw);
+            }
+            for trivia in &lexeme.trailing_trivia {
+                text.push_str(&trivia.raw);
+            }
+        }
+
+        text
+    }
+}
+
+impl std::fmt::Display for Cst<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
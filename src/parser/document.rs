@@ -0,0 +1,503 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::grammar::Grammar;
+use crate::lexer::Lexeme;
+use crate::lexer::Lexer;
+use crate::lexer::LexerError;
+use crate::lexer::LexerRules;
+use crate::lexer::LexerSnapshot;
+use crate::lexer::Position;
+use crate::lexer::Trivia;
+use crate::parser::parse::accepted;
+use crate::parser::parse::process;
+use crate::parser::parse::rejected;
+use crate::parser::parse::restart;
+use crate::parser::Forest;
+use crate::parser::LexAndParseError;
+use crate::parser::ParserColumn;
+use crate::parser::ParserState;
+use crate::parser::Tree;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// An input that is edited over time, for instance in a language server,
+/// together with its [Lexeme]s and the columns of the Earley algorithm,
+/// which are updated incrementally on every edit.
+///
+/// An edit relexes the input from the state of the [Lexer]
+/// before the first [Lexeme] that may have looked at the edited text,
+/// see [LexerRules::lookahead()],
+/// until the [Lexer] is back at the text after the edit
+/// with the same stack of states as before,
+/// and reuses the rest of the [Lexeme]s.
+///
+/// The columns of the Earley algorithm before the first [Lexeme]
+/// that changed are reused,
+/// and so are the ones after the edit
+/// once they no longer depend on the edited [Lexeme]s,
+/// for instance at the start of the next statement.
+#[allow(clippy::type_complexity)]
+pub struct Document<'a, AST> {
+    lexer_rules: &'a LexerRules,
+    grammar:     &'a Grammar<AST>,
+    input:       String,
+    lexemes:     Vec<Rc<Lexeme>>,
+    /// State of the [Lexer] right after each [Lexeme],
+    /// or none if it was in the middle of several [Lexeme]s.
+    snapshots:   Vec<Option<LexerSnapshot>>,
+    /// Error that stopped the [Lexer] after the last [Lexeme].
+    lexer_error: Option<LexerError>,
+    columns:     Vec<ParserColumn<AST>>,
+    /// Parse Trees of the completed states with a single derivation
+    /// that end before the first [Lexeme] changed by the edits.
+    trees:       RefCell<HashMap<u64, Rc<Tree<AST>>>>,
+}
+
+impl<'a, AST> Document<'a, AST> {
+    /// Lex and run the Earley algorithm over the given input.
+    pub fn new(
+        lexer_rules: &'a LexerRules,
+        grammar: &'a Grammar<AST>,
+        input: &str,
+    ) -> Document<'a, AST> {
+        let mut document = Document {
+            lexer_rules,
+            grammar,
+            input: String::new(),
+            lexemes: Vec::new(),
+            snapshots: Vec::new(),
+            lexer_error: None,
+            columns: Vec::new(),
+            trees: RefCell::new(HashMap::new()),
+        };
+
+        document.edit(0..0, input);
+        document
+    }
+
+    /// The current input.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// The [Lexeme]s of the current input,
+    /// up to the first error of the [Lexer], if any.
+    pub fn lexemes(&self) -> &[Rc<Lexeme>] {
+        &self.lexemes
+    }
+
+    /// Replace the given `range` of bytes of the input with `replacement`,
+    /// updating the [Lexeme]s and the columns of the Earley algorithm.
+    ///
+    /// Panics if the range is out of bounds
+    /// or does not lie on [char] boundaries,
+    /// as [String::replace_range()].
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) {
+        let mut input = self.input.clone();
+        input.replace_range(range.clone(), replacement);
+
+        // The Lexer resumes before the first Lexeme
+        // that may have looked at the edited text to be produced.
+        let lookahead = self.lexer_rules.lookahead();
+        let mut first = self.lexemes.partition_point(|lexeme| {
+            lexeme.position_end.byte_index + lookahead < range.start
+        });
+        while first > 0 && self.snapshots[first - 1].is_none() {
+            first -= 1;
+        }
+        let from = first
+            .checked_sub(1)
+            .and_then(|index| self.snapshots[index].clone());
+
+        let base = match &from {
+            Some(snapshot) => snapshot.position.clone(),
+            None => Position { line: 1, column: 1, byte_index: 0 },
+        };
+        let mut old_end = base.clone();
+        old_end.consume(&self.input[base.byte_index..range.end]);
+        let mut new_end = base;
+        new_end.consume(
+            &input[new_end.byte_index..range.start + replacement.len()],
+        );
+
+        let shift = |position: &Position| Position {
+            line:       position.line - old_end.line + new_end.line,
+            column:     if position.line == old_end.line {
+                position.column - old_end.column + new_end.column
+            } else {
+                position.column
+            },
+            byte_index: position.byte_index - old_end.byte_index
+                + new_end.byte_index,
+        };
+
+        let relexed =
+            relex(self.lexer_rules, &input, from.as_ref(), |snapshot| {
+                if snapshot.position.byte_index < new_end.byte_index {
+                    return None;
+                }
+
+                let byte_index = snapshot.position.byte_index
+                    - new_end.byte_index
+                    + old_end.byte_index;
+                let mut index = self.lexemes.partition_point(|lexeme| {
+                    lexeme.position_end.byte_index < byte_index
+                });
+
+                while index < self.lexemes.len()
+                    && self.lexemes[index].position_end.byte_index == byte_index
+                {
+                    if let Some(old_snapshot) = &self.snapshots[index] {
+                        if old_snapshot.position.byte_index == byte_index
+                            && old_snapshot.same_state(snapshot)
+                        {
+                            return Some(index);
+                        }
+                    }
+                    index += 1;
+                }
+
+                None
+            });
+
+        let mut lexemes = self.lexemes[..first].to_vec();
+        let mut snapshots = self.snapshots[..first].to_vec();
+
+        // The Trivia at the end of the input is lexed again.
+        let mut unchanged = first;
+        if let Some(last) = lexemes.last_mut() {
+            if !last.trailing_trivia.is_empty() {
+                Rc::make_mut(last).trailing_trivia.clear();
+                unchanged -= 1;
+            }
+        }
+
+        // The relexed Lexemes that did not change are kept too.
+        let same = relexed
+            .lexemes
+            .iter()
+            .zip(&relexed.snapshots)
+            .zip(self.lexemes[first..].iter().zip(&self.snapshots[first..]))
+            .take_while(|((new, new_snapshot), (old, old_snapshot))| {
+                unchanged == first
+                    && new == old
+                    && match (new_snapshot, old_snapshot) {
+                        (Some(new_snapshot), Some(old_snapshot)) => {
+                            new_snapshot.position == old_snapshot.position
+                                && new_snapshot.same_state(old_snapshot)
+                        }
+                        (new_snapshot, old_snapshot) => {
+                            new_snapshot.is_none() && old_snapshot.is_none()
+                        }
+                    }
+            })
+            .count();
+        lexemes.extend(self.lexemes[first..first + same].iter().cloned());
+        lexemes.extend(relexed.lexemes.into_iter().skip(same));
+        snapshots.extend(relexed.snapshots);
+        unchanged += same;
+
+        // Lexemes after the edit that are reused,
+        // given by their index in the old and in the new Lexemes.
+        let mut reused = None;
+
+        let (trivia, lexer_error) = match relexed.resync {
+            Some(index) => {
+                reused = Some((index + 1, lexemes.len()));
+
+                let moved = old_end != new_end;
+                for (lexeme, snapshot) in self.lexemes[index + 1..]
+                    .iter()
+                    .zip(&self.snapshots[index + 1..])
+                {
+                    lexemes.push(if moved {
+                        Rc::new(shift_lexeme(lexeme, &shift))
+                    } else {
+                        lexeme.clone()
+                    });
+                    snapshots.push(
+                        snapshot
+                            .as_ref()
+                            .map(|snapshot| snapshot.map_position(shift)),
+                    );
+                }
+
+                // The Trivia at the end of the input did not change.
+                let trivia = match self.lexemes.get(index + 1) {
+                    Some(_) => Vec::new(),
+                    None => shift_trivia(
+                        &self.lexemes[index].trailing_trivia,
+                        &shift,
+                    ),
+                };
+                let lexer_error = self.lexer_error.take().map(|mut error| {
+                    error.position = shift(&error.position);
+                    error.byte_index = error.position.byte_index;
+                    error
+                });
+
+                (trivia, lexer_error)
+            }
+            None => (relexed.trivia, relexed.error),
+        };
+
+        if !trivia.is_empty() {
+            if let Some(last) = lexemes.last_mut() {
+                Rc::make_mut(last).trailing_trivia.extend(trivia);
+                unchanged = unchanged.min(lexemes.len() - 1);
+            }
+        }
+
+        // The Parse Trees that end before the first Lexeme that changed.
+        self.trees.get_mut().retain(|_, tree| {
+            tree.lexeme_range()
+                .is_some_and(|lexeme_range| lexeme_range.end <= unchanged)
+        });
+
+        self.input = input;
+        self.lexemes = lexemes;
+        self.snapshots = snapshots;
+        self.lexer_error = lexer_error;
+        self.reparse(unchanged, reused);
+    }
+
+    /// Run the Earley algorithm from the column of the [Lexeme] at `first`,
+    /// reusing the columns before it,
+    /// and the old columns after the `reused` [Lexeme]s
+    /// once the new columns are the same.
+    fn reparse(&mut self, first: usize, reused: Option<(usize, usize)>) {
+        let grammar = self.grammar;
+        let mut old_columns = std::mem::take(&mut self.columns);
+        let mut columns: Vec<ParserColumn<AST>> =
+            old_columns.drain(..first).collect();
+
+        for index in first..=self.lexemes.len() {
+            columns.push(ParserColumn {
+                index,
+                kind: match index {
+                    0 => '^'.to_string(),
+                    _ => self.lexemes[index - 1].kind.clone(),
+                },
+                states: Vec::new(),
+                unique: HashSet::new(),
+            });
+        }
+
+        restart(grammar, &mut columns, first);
+
+        for column_index in first..columns.len() {
+            let successors: Vec<(usize, String)> = columns
+                .get(column_index + 1)
+                .map(|column| (column.index, column.kind.clone()))
+                .into_iter()
+                .collect();
+
+            process(grammar, &mut columns, column_index, &successors, true);
+
+            if let Some((old_reused, new_reused)) = reused {
+                if column_index < new_reused
+                    || column_index + 1 == columns.len()
+                {
+                    continue;
+                }
+
+                // The old columns before `first` are not kept,
+                // so the rest of the input is parsed again.
+                let old_column = (column_index + old_reused)
+                    .checked_sub(new_reused + first)
+                    .and_then(|index| old_columns.get(index));
+                let old_column = match old_column {
+                    Some(old_column) => old_column,
+                    None => continue,
+                };
+
+                if same_column(&columns[column_index], old_column, first) {
+                    let old_index = old_column.index;
+                    columns.truncate(column_index + 1);
+
+                    for old_column in &old_columns[old_index + 1 - first..] {
+                        let mut column = ParserColumn {
+                            index:  old_column.index - old_index + column_index,
+                            kind:   old_column.kind.clone(),
+                            states: Vec::new(),
+                            unique: HashSet::new(),
+                        };
+
+                        for state in &old_column.states {
+                            let mut state = state.clone();
+                            if state.start_column >= first {
+                                state.start_column = state.start_column
+                                    - old_index
+                                    + column_index;
+                            }
+                            state.end_column = usize::MAX;
+                            column.add(state);
+                        }
+
+                        columns.push(column);
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        self.columns = columns;
+    }
+
+    /// Parse the current input.
+    ///
+    /// Return all possible Parse Trees.
+    ///
+    /// The Parse Trees of the rules that end before the edits
+    /// are reused from the previous calls.
+    pub fn parse(&self) -> Result<Vec<Rc<Tree<AST>>>, LexAndParseError<AST>> {
+        if let Some(error) = &self.lexer_error {
+            return Err(LexAndParseError::Lexer(error.clone()));
+        }
+
+        let state = accepted(&self.columns).ok_or_else(|| {
+            LexAndParseError::Parser(rejected(
+                self.grammar,
+                &self.lexemes,
+                &self.columns,
+            ))
+        })?;
+
+        let forest =
+            Forest::new(self.grammar, &self.lexemes, &self.columns, &state)
+                .map_err(LexAndParseError::Parser)?;
+
+        Ok(forest.trees_cached(&mut self.trees.borrow_mut()))
+    }
+}
+
+/// [Lexeme]s produced by [relex()].
+struct Relexed {
+    lexemes:   Vec<Rc<Lexeme>>,
+    snapshots: Vec<Option<LexerSnapshot>>,
+    /// Index of the old [Lexeme] after which the [Lexer] resynchronised.
+    resync:    Option<usize>,
+    /// [Trivia] at the end of the input, if the [Lexer] reached it.
+    trivia:    Vec<Trivia>,
+    error:     Option<LexerError>,
+}
+
+/// Lex the `input` from the given `snapshot`, or from its start,
+/// until `resync` finds an old [Lexeme]
+/// after which the [Lexer] was in the same state.
+fn relex(
+    rules: &LexerRules,
+    input: &str,
+    snapshot: Option<&LexerSnapshot>,
+    resync: impl Fn(&LexerSnapshot) -> Option<usize>,
+) -> Relexed {
+    let mut lexer = match snapshot {
+        Some(snapshot) => Lexer::resume(rules, input, snapshot),
+        None => Lexer::new(rules, input),
+    };
+    let mut relexed = Relexed {
+        lexemes:   Vec::new(),
+        snapshots: Vec::new(),
+        resync:    None,
+        trivia:    Vec::new(),
+        error:     None,
+    };
+
+    while let Some(lexeme) = lexer.next() {
+        match lexeme {
+            Ok(lexeme) => relexed.lexemes.push(Rc::new(lexeme)),
+            Err(error) => {
+                relexed.error = Some(error);
+                return relexed;
+            }
+        }
+
+        let snapshot = lexer.snapshot();
+        relexed.resync = snapshot.as_ref().and_then(&resync);
+        relexed.snapshots.push(snapshot);
+
+        if relexed.resync.is_some() {
+            return relexed;
+        }
+    }
+
+    relexed.trivia = lexer.pending_trivia().to_vec();
+    relexed
+}
+
+/// Whether the `new` column has the same uncompleted states as the `old` one,
+/// and they only depend on the columns before `first`,
+/// so that the columns after them are the same too.
+///
+/// The completed states of a column are not used by the columns after it.
+fn same_column<AST>(
+    new: &ParserColumn<AST>,
+    old: &ParserColumn<AST>,
+    first: usize,
+) -> bool {
+    let digest = |state: &ParserState<AST>, start_column: usize| {
+        let mut state = state.clone();
+        state.start_column = start_column;
+        state.end_column = usize::MAX;
+        state.hash_me()
+    };
+
+    let mut digests = HashSet::new();
+    for state in new.states.iter().filter(|state| !state.completed()) {
+        if state.start_column >= first && state.start_column != new.index {
+            return false;
+        }
+        digests.insert(digest(state, state.start_column));
+    }
+
+    let mut len = 0;
+    old.states.iter().filter(|state| !state.completed()).all(|state| {
+        let start_column = if state.start_column < first {
+            state.start_column
+        } else if state.start_column == old.index {
+            new.index
+        } else {
+            return false;
+        };
+        len += 1;
+
+        digests.contains(&digest(state, start_column))
+    }) && len == digests.len()
+}
+
+/// Copy of the `lexeme` with its positions moved by `shift`.
+fn shift_lexeme(
+    lexeme: &Lexeme,
+    shift: &impl Fn(&Position) -> Position,
+) -> Lexeme {
+    Lexeme {
+        kind:            lexeme.kind.clone(),
+        raw:             lexeme.raw.clone(),
+        position:        shift(&lexeme.position),
+        position_end:    shift(&lexeme.position_end),
+        leading_trivia:  shift_trivia(&lexeme.leading_trivia, shift),
+        trailing_trivia: shift_trivia(&lexeme.trailing_trivia, shift),
+    }
+}
+
+/// Move the [Trivia] by the given `shift` of their positions.
+fn shift_trivia(
+    trivia: &[Trivia],
+    shift: &impl Fn(&Position) -> Position,
+) -> Vec<Trivia> {
+    trivia
+        .iter()
+        .map(|trivia| Trivia {
+            kind:         trivia.kind.clone(),
+            raw:          trivia.raw.clone(),
+            position:     shift(&trivia.position),
+            position_end: shift(&trivia.position_end),
+        })
+        .collect()
+}
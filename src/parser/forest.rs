@@ -0,0 +1,511 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::grammar::Grammar;
+use crate::grammar::ProductionKind;
+use crate::lexer::Lexeme;
+use crate::lexer::LexemeLattice;
+use crate::parser::tree::satisfies_disambiguation;
+use crate::parser::tree::Disambiguated;
+use crate::parser::tree::NonAssociativeChain;
+use crate::parser::ParseError;
+use crate::parser::ParserColumn;
+use crate::parser::ParserState;
+use crate::parser::Tree;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Shared Packed Parse Forest:
+/// a compact representation of all the Parse Trees of an input.
+///
+/// Subtrees are shared between the Parse Trees that contain them,
+/// and the alternative derivations of a node are packed together,
+/// so the size of a [Forest] is polynomial in the length of the input
+/// even if the number of Parse Trees is exponential.
+///
+/// Normally you create a [Forest]
+/// by using [parse_forest()](crate::parser::parse_forest()).
+pub struct Forest<AST> {
+    lexemes:  Vec<Rc<Lexeme>>,
+    /// Byte indexes where the [Lexeme]s after each column start,
+    /// and where the [Lexeme]s before it end.
+    bounds:   Vec<(usize, usize)>,
+    families: Vec<ForestFamily>,
+    nodes:    Vec<ForestNode<AST>>,
+    root:     Option<usize>,
+}
+
+/// Completed derivation of a rule over a range of the input.
+struct ForestNode<AST> {
+    state:  ParserState<AST>,
+    /// Derivations of the symbols of the production,
+    /// or none if the production is empty.
+    family: Option<usize>,
+    count:  usize,
+}
+
+/// Derivations of the symbols of a production up to a given symbol,
+/// ending at a given column.
+///
+/// Each alternative is the derivation of the symbol,
+/// followed by the derivations of the symbols before it,
+/// or none if it is the first symbol.
+struct ForestFamily {
+    alternatives: Vec<(ForestChild, Option<usize>)>,
+    count:        usize,
+}
+
+enum ForestChild {
+    Leaf(usize),
+    Node(usize),
+}
+
+impl<AST> Forest<AST> {
+    pub(crate) fn new(
+        grammar: &Grammar<AST>,
+        lexemes: &[Rc<Lexeme>],
+        columns: &[ParserColumn<AST>],
+        state: &ParserState<AST>,
+    ) -> Result<Forest<AST>, ParseError<AST>> {
+        let end =
+            lexemes.last().map_or(0, |lexeme| lexeme.position_end.byte_index);
+        let bounds = (0..=lexemes.len())
+            .map(|column| {
+                let start = lexemes
+                    .get(column)
+                    .map_or(end, |lexeme| lexeme.position.byte_index);
+                let end = match column {
+                    0 => start,
+                    _ => lexemes[column - 1].position_end.byte_index,
+                };
+
+                (start, end)
+            })
+            .collect();
+        let incoming: Vec<Vec<(usize, usize)>> = (0..=lexemes.len())
+            .map(|column| match column {
+                0 => Vec::new(),
+                _ => vec![(column - 1, column - 1)],
+            })
+            .collect();
+
+        Forest::build(
+            grammar,
+            lexemes.to_vec(),
+            bounds,
+            &incoming,
+            columns,
+            state,
+        )
+    }
+
+    /// Create a [Forest] of the tokenisations of a [LexemeLattice],
+    /// given the columns of
+    /// [earley_lattice()](crate::parser::earley_lattice())
+    /// and the completed start state of one of its final nodes.
+    ///
+    /// The leaves of the Parse Trees are the [Lexeme]s of the edges,
+    /// and their ranges refer to the tokenisation they come from.
+    pub(crate) fn new_lattice(
+        grammar: &Grammar<AST>,
+        lattice: &LexemeLattice,
+        columns: &[ParserColumn<AST>],
+        state: &ParserState<AST>,
+    ) -> Result<Forest<AST>, ParseError<AST>> {
+        let mut starts: Vec<Option<usize>> = vec![None; lattice.len];
+        let mut ends: Vec<Option<usize>> = vec![None; lattice.len];
+        let mut incoming: Vec<Vec<(usize, usize)>> =
+            vec![Vec::new(); lattice.len];
+
+        for (edge, (from, to, lexeme)) in lattice.edges.iter().enumerate() {
+            starts[*from].get_or_insert(lexeme.position.byte_index);
+            ends[*to].get_or_insert(lexeme.position_end.byte_index);
+            incoming[*to].push((edge, *from));
+        }
+
+        let bounds = starts
+            .into_iter()
+            .zip(ends)
+            .map(|(start, end)| {
+                let start = start.or(end).unwrap_or(0);
+
+                (start, end.unwrap_or(start))
+            })
+            .collect();
+        let lexemes =
+            lattice.edges.iter().map(|(_, _, lexeme)| lexeme.clone()).collect();
+
+        Forest::build(grammar, lexemes, bounds, &incoming, columns, state)
+    }
+
+    /// Create a [Forest] whose leaves are the given [Lexeme]s,
+    /// where `incoming` has, for every column,
+    /// the indexes of the [Lexeme]s that lead to it
+    /// and the columns they come from.
+    fn build(
+        grammar: &Grammar<AST>,
+        lexemes: Vec<Rc<Lexeme>>,
+        bounds: Vec<(usize, usize)>,
+        incoming: &[Vec<(usize, usize)>],
+        columns: &[ParserColumn<AST>],
+        state: &ParserState<AST>,
+    ) -> Result<Forest<AST>, ParseError<AST>> {
+        let mut builder = ForestBuilder {
+            grammar,
+            columns,
+            incoming,
+            chains: Vec::new(),
+            family_ids: HashMap::new(),
+            forest: Forest {
+                lexemes,
+                bounds,
+                families: Vec::new(),
+                nodes: Vec::new(),
+                root: None,
+            },
+            node_ids: HashMap::new(),
+        };
+
+        builder.forest.root = builder.node(state);
+
+        if builder.forest.root.is_none() {
+            if let Some(error) = NonAssociativeChain::outermost_parse_error(
+                builder.chains,
+                grammar,
+                &builder.forest.lexemes,
+            ) {
+                return Err(error);
+            }
+        }
+
+        Ok(builder.forest)
+    }
+
+    /// Number of Parse Trees in this [Forest].
+    ///
+    /// Saturates at [usize::MAX].
+    pub fn count(&self) -> usize {
+        self.root.map_or(0, |root| self.nodes[root].count)
+    }
+
+    /// Extract the Parse Tree at position `index`,
+    /// or none if `index` is not less than [Forest::count()].
+    ///
+    /// Parse Trees are numbered
+    /// in the same order as [parse()](crate::parser::parse()) returns them.
+    pub fn tree(&self, index: usize) -> Option<Rc<Tree<AST>>> {
+        match self.root {
+            Some(root) if index < self.nodes[root].count => {
+                Some(self.node_tree(root, index, 0, None))
+            }
+            _ => None,
+        }
+    }
+
+    /// Lazily iterate the Parse Trees in this [Forest],
+    /// in the same order as [parse()](crate::parser::parse()) returns them.
+    pub fn trees(&self) -> impl Iterator<Item = Rc<Tree<AST>>> + '_ {
+        (0..self.count())
+            .map(|index| self.node_tree(self.root.unwrap(), index, 0, None))
+    }
+
+    /// Extract all the Parse Trees in this [Forest],
+    /// reusing the subtrees in the `cache` of the completed states
+    /// that have a single derivation,
+    /// and adding the ones that are built.
+    pub(crate) fn trees_cached(
+        &self,
+        cache: &mut HashMap<u64, Rc<Tree<AST>>>,
+    ) -> Vec<Rc<Tree<AST>>> {
+        (0..self.count())
+            .map(|index| {
+                self.node_tree(self.root.unwrap(), index, 0, Some(&mut *cache))
+            })
+            .collect()
+    }
+
+    fn child_count(&self, child: &ForestChild) -> usize {
+        match child {
+            ForestChild::Leaf(_) => 1,
+            ForestChild::Node(node_id) => self.nodes[*node_id].count,
+        }
+    }
+
+    fn family_count(&self, family: Option<usize>) -> usize {
+        family.map_or(1, |family_id| self.families[family_id].count)
+    }
+
+    /// Extract the Parse Tree at position `index` of the node `node_id`,
+    /// whose first [Lexeme] is the one at `start` in its tokenisation.
+    ///
+    /// The Parse Trees of the nodes with a single derivation
+    /// are taken from the `cache` if they are there,
+    /// and added to it otherwise.
+    fn node_tree(
+        &self,
+        node_id: usize,
+        index: usize,
+        start: usize,
+        mut cache: Option<&mut HashMap<u64, Rc<Tree<AST>>>>,
+    ) -> Rc<Tree<AST>> {
+        let node = &self.nodes[node_id];
+        let key = match (node.count, cache.as_deref()) {
+            (1, Some(cache)) => {
+                let key = node.state.hash_me();
+                if let Some(tree) = cache.get(&key) {
+                    return tree.clone();
+                }
+
+                Some(key)
+            }
+            _ => None,
+        };
+        let mut family = node.family;
+        let mut index = index;
+        let mut children = Vec::new();
+
+        while let Some(family_id) = family {
+            let mut found = None;
+
+            for (child, rest) in &self.families[family_id].alternatives {
+                let rest_count = self.family_count(*rest);
+                let weight = self.child_count(child).saturating_mul(rest_count);
+
+                if index < weight {
+                    found = Some((child, *rest, rest_count));
+                    break;
+                }
+
+                index -= weight;
+            }
+
+            let (child, rest, rest_count) = found.unwrap();
+
+            children.push((child, index / rest_count));
+
+            index %= rest_count;
+            family = rest;
+        }
+
+        let mut end = start;
+        let leaves = children
+            .into_iter()
+            .rev()
+            .map(|(child, index)| match child {
+                ForestChild::Leaf(lexeme_index) => {
+                    end += 1;
+
+                    Rc::new(Tree::Leaf(self.lexemes[*lexeme_index].clone()))
+                }
+                ForestChild::Node(child_id) => {
+                    let tree = self.node_tree(
+                        *child_id,
+                        index,
+                        end,
+                        cache.as_deref_mut(),
+                    );
+                    if let Tree::Node { lexeme_range, .. } = &*tree {
+                        end = lexeme_range.end;
+                    }
+
+                    tree
+                }
+            })
+            .collect();
+
+        let (first, last) = (node.state.start_column, node.state.end_column);
+        let byte_range = if first == last {
+            self.bounds[first].0..self.bounds[first].0
+        } else {
+            self.bounds[first].0..self.bounds[last].1
+        };
+
+        let tree = Rc::new(Tree::Node {
+            rule_name: node.state.rule_name.clone(),
+            production: node.state.production.clone(),
+            leaves,
+            lexeme_range: start..end,
+            byte_range,
+        });
+
+        if let (Some(key), Some(cache)) = (key, cache) {
+            cache.insert(key, tree.clone());
+        }
+
+        tree
+    }
+}
+
+struct ForestBuilder<'a, AST> {
+    grammar:    &'a Grammar<AST>,
+    columns:    &'a [ParserColumn<AST>],
+    /// Indexes of the [Lexeme]s that lead to each column,
+    /// and the columns they come from.
+    incoming:   &'a [Vec<(usize, usize)>],
+    chains:     Vec<NonAssociativeChain<AST>>,
+    family_ids: HashMap<(u64, usize, usize), Option<usize>>,
+    forest:     Forest<AST>,
+    node_ids:   HashMap<u64, Option<usize>>,
+}
+
+impl<'a, AST> ForestBuilder<'a, AST> {
+    /// Identifier of the node for the completed `state`,
+    /// or none if it has no derivations.
+    fn node(&mut self, state: &ParserState<AST>) -> Option<usize> {
+        let key = state.hash_me();
+        if let Some(node_id) = self.node_ids.get(&key) {
+            return *node_id;
+        }
+
+        let (family, count) = match state.production.symbols.len() {
+            0 => (None, 1),
+            symbols => {
+                match self.family(state, symbols - 1, state.end_column) {
+                    Some(family_id) => {
+                        (Some(family_id), self.forest.families[family_id].count)
+                    }
+                    None => (None, 0),
+                }
+            }
+        };
+
+        let node_id = if count > 0 {
+            self.forest.nodes.push(ForestNode {
+                state: state.clone(),
+                family,
+                count,
+            });
+            Some(self.forest.nodes.len() - 1)
+        } else {
+            None
+        };
+
+        self.node_ids.insert(key, node_id);
+
+        node_id
+    }
+
+    /// Identifier of the family for the symbols of the production of `state`
+    /// up to `symbol_index`, ending at `end_column`,
+    /// or none if it has no derivations.
+    fn family(
+        &mut self,
+        state: &ParserState<AST>,
+        symbol_index: usize,
+        end_column: usize,
+    ) -> Option<usize> {
+        let key = (state.hash_me(), symbol_index, end_column);
+        if let Some(family_id) = self.family_ids.get(&key) {
+            return *family_id;
+        }
+
+        let mut alternatives = Vec::new();
+        let mut count: usize = 0;
+
+        match &state.production.kind {
+            ProductionKind::Lexemes => {
+                for (lexeme_index, from) in &self.incoming[end_column] {
+                    let kind = &self.forest.lexemes[*lexeme_index].kind;
+
+                    if !state.production.matches(symbol_index, kind)
+                        || (symbol_index == 0 && *from != state.start_column)
+                    {
+                        continue;
+                    }
+
+                    if let Some(rest) = self.rest(state, symbol_index, *from) {
+                        alternatives
+                            .push((ForestChild::Leaf(*lexeme_index), rest));
+                        count = count
+                            .saturating_add(self.forest.family_count(rest));
+                    }
+                }
+            }
+            ProductionKind::Rules => {
+                let rule_name = &state.production.symbols[symbol_index];
+                let columns = self.columns;
+
+                for state_partial in columns[end_column]
+                    .states
+                    .iter()
+                    .take_while(|state_partial| *state_partial != state)
+                    .filter(|state_partial| {
+                        state_partial.completed()
+                            && *state_partial.rule_name == *rule_name
+                            && (symbol_index > 0
+                                || state_partial.start_column
+                                    == state.start_column)
+                    })
+                {
+                    let disambiguated = satisfies_disambiguation(
+                        self.grammar,
+                        state_partial,
+                        state,
+                    );
+
+                    if let Disambiguated::No = disambiguated {
+                        continue;
+                    }
+
+                    let child_id = match self.node(state_partial) {
+                        Some(child_id) => child_id,
+                        None => continue,
+                    };
+                    let rest = match self.rest(
+                        state,
+                        symbol_index,
+                        state_partial.start_column,
+                    ) {
+                        Some(rest) => rest,
+                        None => continue,
+                    };
+
+                    if let Disambiguated::NonAssociative = disambiguated {
+                        self.chains.push(NonAssociativeChain {
+                            operand: state_partial.clone(),
+                            parent:  state.clone(),
+                        });
+                        continue;
+                    }
+
+                    let weight = self.forest.nodes[child_id]
+                        .count
+                        .saturating_mul(self.forest.family_count(rest));
+
+                    alternatives.push((ForestChild::Node(child_id), rest));
+                    count = count.saturating_add(weight);
+                }
+            }
+        }
+
+        let family_id = if alternatives.is_empty() {
+            None
+        } else {
+            self.forest.families.push(ForestFamily { alternatives, count });
+            Some(self.forest.families.len() - 1)
+        };
+
+        self.family_ids.insert(key, family_id);
+
+        family_id
+    }
+
+    /// Derivations of the symbols before `symbol_index`,
+    /// ending at `end_column`.
+    ///
+    /// Returns `Some(None)` if there are no such symbols,
+    /// and none if there are no such derivations.
+    fn rest(
+        &mut self,
+        state: &ParserState<AST>,
+        symbol_index: usize,
+        end_column: usize,
+    ) -> Option<Option<usize>> {
+        if symbol_index == 0 {
+            Some(None)
+        } else {
+            self.family(state, symbol_index - 1, end_column).map(Some)
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::LexerError;
+use crate::parser::ParseError;
+
+/// Internal representation of an error encountered by
+/// [crate::parser::lex_and_parse()].
+pub enum LexAndParseError<AST> {
+    /// The [Lexer](crate::lexer::Lexer) found an error.
+    Lexer(LexerError),
+    /// The parser found an error.
+    Parser(ParseError<AST>),
+}
+
+impl<AST> std::fmt::Debug for LexAndParseError<AST> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl<AST> std::fmt::Display for LexAndParseError<AST> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexAndParseError::Lexer(error) => write!(f, "{error}"),
+            LexAndParseError::Parser(error) => write!(f, "{error}"),
+        }
+    }
+}
@@ -6,15 +6,35 @@
 //!
 //! Please read the [crate documentation](crate) for more information and examples.
 
+mod cst;
+mod document;
+mod forest;
+mod lex_and_parse_error;
 mod parse;
 mod parse_error;
+mod parse_trees;
 mod parser_column;
 mod parser_state;
+mod recovered_forest;
+mod recovery;
 mod tree;
 
+pub use cst::Cst;
+pub use document::Document;
+pub use forest::Forest;
+pub use lex_and_parse_error::LexAndParseError;
 pub use parse::earley;
+pub use parse::earley_lattice;
+pub use parse::lex_and_parse;
 pub use parse::parse;
+pub use parse::parse_forest;
+pub use parse::parse_iter;
+pub use parse::parse_lattice;
+pub use parse::parse_str;
 pub use parse_error::ParseError;
+pub use parse_trees::ParseTrees;
 pub use parser_column::ParserColumn;
 pub use parser_state::ParserState;
+pub use recovered_forest::RecoveredForest;
+pub use recovery::parse_with_recovery;
 pub use tree::Tree;
@@ -6,9 +6,15 @@ use crate::grammar::Grammar;
 use crate::grammar::GrammarRule;
 use crate::grammar::ProductionKind;
 use crate::grammar::START_RULE_NAME;
+use crate::lexer::lex_chars;
 use crate::lexer::Lexeme;
-use crate::parser::tree::build;
+use crate::lexer::LexemeLattice;
+use crate::lexer::Lexer;
+use crate::lexer::LexerRules;
+use crate::parser::Forest;
+use crate::parser::LexAndParseError;
 use crate::parser::ParseError;
+use crate::parser::ParseTrees;
 use crate::parser::ParserColumn;
 use crate::parser::ParserState;
 use crate::parser::Tree;
@@ -16,17 +22,15 @@ use std::collections::HashSet;
 use std::rc::Rc;
 
 fn predict<AST>(
-    columns: &mut Vec<ParserColumn<AST>>,
+    columns: &mut [ParserColumn<AST>],
     column_index: usize,
+    successors: &[(usize, String)],
     rule: &GrammarRule<AST>,
 ) {
     for production in &rule.productions {
-        if column_index + 1 < columns.len()
+        if !successors.is_empty()
             && !production.target_lexemes.borrow().is_empty()
-            && !production
-                .target_lexemes
-                .borrow()
-                .contains(&columns[column_index + 1].kind)
+            && !successors.iter().any(|(_, kind)| production.targets(kind))
         {
             continue;
         }
@@ -43,9 +47,10 @@ fn predict<AST>(
 }
 
 fn scan<AST>(
-    columns: &mut Vec<ParserColumn<AST>>,
+    columns: &mut [ParserColumn<AST>],
     column_index: usize,
     state_index: usize,
+    target_column_index: usize,
 ) {
     let state = &columns[column_index].states[state_index];
     let new_state = ParserState {
@@ -55,11 +60,11 @@ fn scan<AST>(
         end_column:   usize::MAX,
         dot_index:    state.dot_index + 1,
     };
-    columns[column_index + 1].add(new_state);
+    columns[target_column_index].add(new_state);
 }
 
 fn complete<AST>(
-    columns: &mut Vec<ParserColumn<AST>>,
+    columns: &mut [ParserColumn<AST>],
     column_index: usize,
     state_index: usize,
 ) {
@@ -109,17 +114,65 @@ pub fn parse<AST>(
     grammar: &Grammar<AST>,
     lexemes: &[Rc<Lexeme>],
 ) -> Result<Vec<Rc<Tree<AST>>>, ParseError<AST>> {
-    let mut columns: Vec<ParserColumn<AST>> = earley(grammar, lexemes);
+    Ok(parse_forest(grammar, lexemes)?.trees().collect())
+}
 
-    let mut parent = None;
-    for state in &columns.last().unwrap().states {
-        if *state.rule_name == START_RULE_NAME && state.completed() {
-            parent = Some(state.clone());
-            break;
-        }
-    }
+/// Parse the characters of the `input` with the given scannerless [Grammar],
+/// as split by [lex_chars()].
+///
+/// Return all possible Parse Trees.
+pub fn parse_str<AST>(
+    grammar: &Grammar<AST>,
+    input: &str,
+) -> Result<Vec<Rc<Tree<AST>>>, ParseError<AST>> {
+    parse(grammar, &lex_chars(input))
+}
+
+/// Parse the provided [Lexeme]s with the given [Grammar].
+///
+/// Return a [Forest] that shares and packs all possible Parse Trees.
+pub fn parse_forest<AST>(
+    grammar: &Grammar<AST>,
+    lexemes: &[Rc<Lexeme>],
+) -> Result<Forest<AST>, ParseError<AST>> {
+    let (columns, state) = completed_columns(grammar, lexemes)?;
+
+    Forest::new(grammar, lexemes, &columns, &state)
+}
+
+/// Parse the provided [Lexeme]s with the given [Grammar].
+///
+/// Return an iterator that extracts the Parse Trees on demand
+/// from the [Forest] of [parse_forest()],
+/// in the same order as [parse()] returns them.
+pub fn parse_iter<AST>(
+    grammar: &Grammar<AST>,
+    lexemes: &[Rc<Lexeme>],
+) -> Result<ParseTrees<AST>, ParseError<AST>> {
+    Ok(ParseTrees::new(parse_forest(grammar, lexemes)?))
+}
+
+/// Run the Earley algorithm and keep only the completed states,
+/// returning them together with the completed start state.
+#[allow(clippy::type_complexity)]
+fn completed_columns<AST>(
+    grammar: &Grammar<AST>,
+    lexemes: &[Rc<Lexeme>],
+) -> Result<(Vec<ParserColumn<AST>>, ParserState<AST>), ParseError<AST>> {
+    let columns: Vec<ParserColumn<AST>> = earley(grammar, lexemes);
 
-    if let Some(state) = parent {
+    keep_completed(grammar, lexemes, columns)
+}
+
+/// Keep only the completed states of the `columns`,
+/// returning them together with the completed start state.
+#[allow(clippy::type_complexity)]
+pub(crate) fn keep_completed<AST>(
+    grammar: &Grammar<AST>,
+    lexemes: &[Rc<Lexeme>],
+    mut columns: Vec<ParserColumn<AST>>,
+) -> Result<(Vec<ParserColumn<AST>>, ParserState<AST>), ParseError<AST>> {
+    if let Some(state) = accepted(&columns) {
         for column in columns.iter_mut() {
             column.states = column
                 .states
@@ -129,31 +182,63 @@ pub fn parse<AST>(
                 .collect();
         }
 
-        Ok(build(grammar, lexemes, &columns, &state))
-    } else if lexemes.is_empty() {
-        Err(ParseError { at: None, states: columns[0].states.clone() })
+        Ok((columns, state))
     } else {
-        let column = columns
-            .iter()
-            .rev()
-            .find(|column| !column.states.is_empty())
-            .unwrap();
-
-        Err(ParseError {
-            at:     lexemes.get(column.index.overflowing_sub(1).0).cloned(),
-            states: column.states.clone(),
-        })
+        Err(rejected(grammar, lexemes, &columns))
     }
 }
 
+/// The completed start state of the last of the `columns`,
+/// or none if they do not accept the input.
+pub(crate) fn accepted<AST>(
+    columns: &[ParserColumn<AST>],
+) -> Option<ParserState<AST>> {
+    columns
+        .last()
+        .unwrap()
+        .states
+        .iter()
+        .find(|state| *state.rule_name == START_RULE_NAME && state.completed())
+        .cloned()
+}
+
+/// Explain why the `columns` do not accept the `lexemes`.
+pub(crate) fn rejected<AST>(
+    grammar: &Grammar<AST>,
+    lexemes: &[Rc<Lexeme>],
+    columns: &[ParserColumn<AST>],
+) -> ParseError<AST> {
+    let column = columns
+        .iter()
+        .rev()
+        .find(|column| !column.states.is_empty())
+        .unwrap_or(&columns[0]);
+
+    ParseError::unexpected(grammar, lexemes, column)
+}
+
 /// Parse the provided [Lexemes](Lexeme) with the given [Grammar]
 /// and the [Earley algorithm](https://en.wikipedia.org/wiki/Earley_parser).
 pub fn earley<AST>(
     grammar: &Grammar<AST>,
     lexemes: &[Rc<Lexeme>],
 ) -> Vec<ParserColumn<AST>> {
-    let mut columns: Vec<ParserColumn<AST>> = (0..=lexemes.len())
-        .map(|index| {
+    earley_from(grammar, lexemes, &[], 0)
+}
+
+/// Run the Earley algorithm over the `lexemes`,
+/// reusing the `columns` before the column `first`,
+/// which were computed for the same [Lexeme]s before `first`.
+pub(crate) fn earley_from<AST>(
+    grammar: &Grammar<AST>,
+    lexemes: &[Rc<Lexeme>],
+    columns: &[ParserColumn<AST>],
+    first: usize,
+) -> Vec<ParserColumn<AST>> {
+    let mut columns: Vec<ParserColumn<AST>> = columns[..first]
+        .iter()
+        .cloned()
+        .chain((first..=lexemes.len()).map(|index| {
             if index == 0 {
                 ParserColumn {
                     index,
@@ -169,63 +254,419 @@ pub fn earley<AST>(
                     unique: HashSet::new(),
                 }
             }
+        }))
+        .collect();
+
+    restart(grammar, &mut columns, first);
+
+    for column_index in first..columns.len() {
+        let successors: Vec<(usize, String)> = columns
+            .get(column_index + 1)
+            .map(|column| (column.index, column.kind.clone()))
+            .into_iter()
+            .collect();
+
+        process(grammar, &mut columns, column_index, &successors, true);
+    }
+
+    // println!();
+    // println!("Columns:");
+    // for (column_index, column) in columns.iter().enumerate() {
+    //     println!("  {column_index}");
+    //     for state in &column.states {
+    //         println!("    {state}");
+    //     }
+    // }
+
+    columns
+}
+
+/// Parse the alternative tokenisations of a [LexemeLattice]
+/// with the given [Grammar]
+/// and the [Earley algorithm](https://en.wikipedia.org/wiki/Earley_parser).
+///
+/// There is one column per node of the [LexemeLattice],
+/// and the states of a column are scanned
+/// into the columns of the nodes its [Lexeme]s lead to.
+pub fn earley_lattice<AST>(
+    grammar: &Grammar<AST>,
+    lattice: &LexemeLattice,
+) -> Vec<ParserColumn<AST>> {
+    let mut successors: Vec<Vec<(usize, String)>> = vec![vec![]; lattice.len];
+    let mut columns: Vec<ParserColumn<AST>> = (0..lattice.len)
+        .map(|index| ParserColumn {
+            index,
+            kind: '^'.to_string(),
+            states: Vec::new(),
+            unique: HashSet::new(),
+        })
+        .collect();
+
+    for (from, to, lexeme) in &lattice.edges {
+        successors[*from].push((*to, lexeme.kind.clone()));
+        columns[*to].kind = lexeme.kind.clone();
+    }
+
+    if let Some(column) = columns.first_mut() {
+        start(grammar, column);
+    }
+
+    for (column_index, successors) in successors.iter().enumerate() {
+        process(grammar, &mut columns, column_index, successors, false);
+    }
+
+    columns
+}
+
+/// Parse the alternative tokenisations of a [LexemeLattice]
+/// with the given [Grammar].
+///
+/// Return the tokenisations that lead to a valid parse,
+/// together with all their possible Parse Trees.
+///
+/// The parser follows all the tokenisations at once,
+/// discarding the [Lexeme]s that the [Grammar] does not expect,
+/// and the Parse Trees are built from a single [Forest]
+/// per final node of the [LexemeLattice],
+/// so the tokenisations that do not parse are never enumerated.
+#[allow(clippy::type_complexity)]
+pub fn parse_lattice<AST>(
+    grammar: &Grammar<AST>,
+    lattice: &LexemeLattice,
+) -> Result<Vec<(Vec<Rc<Lexeme>>, Vec<Rc<Tree<AST>>>)>, ParseError<AST>> {
+    let mut columns = earley_lattice(grammar, lattice);
+
+    let roots: Vec<ParserState<AST>> = lattice
+        .finals
+        .iter()
+        .filter_map(|node| {
+            columns[*node]
+                .states
+                .iter()
+                .find(|state| {
+                    *state.rule_name == START_RULE_NAME && state.completed()
+                })
+                .cloned()
         })
         .collect();
 
+    if roots.is_empty() {
+        let column = columns
+            .iter()
+            .rev()
+            .find(|column| !column.states.is_empty())
+            .unwrap_or(&columns[0]);
+        let lexeme_from = |node: usize| {
+            lattice
+                .edges
+                .iter()
+                .find(|(from, _, _)| *from == node)
+                .map(|(_, _, lexeme)| lexeme.clone())
+        };
+        let lexeme_to = |node: usize| {
+            lattice
+                .edges
+                .iter()
+                .find(|(_, to, _)| *to == node)
+                .map(|(_, _, lexeme)| lexeme.clone())
+        };
+
+        return Err(ParseError::unexpected_between(
+            grammar,
+            lexeme_to(column.index),
+            lexeme_from(column.index),
+            column,
+        ));
+    }
+
+    for column in columns.iter_mut() {
+        column.states.retain(|state| state.completed());
+    }
+
+    let mut results: Vec<(Vec<Rc<Lexeme>>, Vec<Rc<Tree<AST>>>)> = Vec::new();
+
+    for state in &roots {
+        let forest =
+            match Forest::new_lattice(grammar, lattice, &columns, state) {
+                Ok(forest) => forest,
+                Err(_) => continue,
+            };
+
+        for tree in forest.trees() {
+            let lexemes: Vec<Rc<Lexeme>> = tree
+                .traverse_in_pre_order()
+                .into_iter()
+                .filter_map(|tree| match tree {
+                    Tree::Leaf(lexeme) => Some(lexeme.clone()),
+                    _ => None,
+                })
+                .collect();
+
+            let tokenisation = results.iter_mut().find(|(tokenisation, _)| {
+                tokenisation.len() == lexemes.len()
+                    && tokenisation
+                        .iter()
+                        .zip(&lexemes)
+                        .all(|(left, right)| Rc::ptr_eq(left, right))
+            });
+
+            match tokenisation {
+                Some((_, trees)) => trees.push(tree),
+                None => results.push((lexemes, vec![tree])),
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// Add the state of the start rule to the first `column`.
+/// Fill the column `first` as the Earley algorithm does
+/// before processing it,
+/// given that the columns before it have been processed.
+pub(crate) fn restart<AST>(
+    grammar: &Grammar<AST>,
+    columns: &mut [ParserColumn<AST>],
+    first: usize,
+) {
+    if first == 0 {
+        start(grammar, &mut columns[0]);
+        return;
+    }
+
+    for state_index in 0..columns[first - 1].states.len() {
+        let state = &columns[first - 1].states[state_index];
+
+        if let ProductionKind::Lexemes = state.production.kind {
+            if !state.completed()
+                && state
+                    .production
+                    .matches(state.dot_index, &columns[first].kind)
+            {
+                scan(columns, first - 1, state_index, first);
+            }
+        }
+    }
+}
+
+fn start<AST>(grammar: &Grammar<AST>, column: &mut ParserColumn<AST>) {
     let rule_name = Rc::new(START_RULE_NAME.to_string());
-    columns[0].add(ParserState {
-        production: grammar.rules.get(&rule_name).unwrap().productions[0]
-            .clone(),
-        rule_name,
-        start_column: 0,
-        end_column: usize::MAX,
-        dot_index: 0,
-    });
-
-    for column_index in 0..columns.len() {
-        let mut state_index = 0;
-        let mut state_len = columns[column_index].states.len();
-        let mut predicted_names = HashSet::new();
+    if let Some(rule) = grammar.rules.get(&rule_name) {
+        column.add(ParserState {
+            production: rule.productions[0].clone(),
+            rule_name,
+            start_column: 0,
+            end_column: usize::MAX,
+            dot_index: 0,
+        });
+    }
+}
 
-        while state_index < state_len {
-            let state = &columns[column_index].states[state_index];
+/// Predict, scan and complete the states of the given column,
+/// scanning into the `successors` columns
+/// whose incoming [Lexeme] has the given kind.
+///
+/// The `columns` are `linear` if each one follows the previous one,
+/// which is needed to check the strings rejected by the [Grammar].
+pub(crate) fn process<AST>(
+    grammar: &Grammar<AST>,
+    columns: &mut [ParserColumn<AST>],
+    column_index: usize,
+    successors: &[(usize, String)],
+    linear: bool,
+) {
+    let mut state_index = 0;
+    let mut state_len = columns[column_index].states.len();
+    let mut predicted_names = HashSet::new();
+    let mut restricted_indexes = HashSet::new();
+
+    while state_index < state_len {
+        let state = &columns[column_index].states[state_index];
 
-            if columns[column_index].states[state_index].completed() {
-                complete(&mut columns, column_index, state_index);
+        if columns[column_index].states[state_index].completed() {
+            if restricted(
+                grammar,
+                columns,
+                column_index,
+                state,
+                successors,
+                linear,
+            ) {
+                restricted_indexes.insert(state_index);
             } else {
-                match state.production.kind {
-                    ProductionKind::Rules => {
-                        let rule_name = state.next_symbol().unwrap().clone();
-                        if !predicted_names.contains(&rule_name) {
-                            let rule = grammar.rules.get(&rule_name).unwrap();
-                            predicted_names.insert(rule_name);
-                            predict(&mut columns, column_index, rule);
-                        }
+                complete(columns, column_index, state_index);
+            }
+        } else {
+            match state.production.kind {
+                ProductionKind::Rules => {
+                    let rule_name = state.next_symbol().unwrap().clone();
+                    if !predicted_names.contains(&rule_name) {
+                        let rule = grammar.rules.get(&rule_name).unwrap();
+                        predicted_names.insert(rule_name);
+                        predict(columns, column_index, successors, rule);
                     }
-                    ProductionKind::Lexemes => {
-                        if column_index + 1 < columns.len()
-                            && *state.next_symbol().unwrap()
-                                == columns[column_index + 1].kind
-                        {
-                            scan(&mut columns, column_index, state_index);
-                        }
+                }
+                ProductionKind::Lexemes => {
+                    let targets: Vec<usize> = successors
+                        .iter()
+                        .filter(|(_, kind)| {
+                            state.production.matches(state.dot_index, kind)
+                        })
+                        .map(|(successor, _)| *successor)
+                        .collect();
+
+                    for successor in targets {
+                        scan(columns, column_index, state_index, successor);
                     }
                 }
             }
+        }
 
+        state_index += 1;
+        state_len = columns[column_index].states.len();
+    }
+
+    // Restricted states cannot be part of a Parse Tree either.
+    if !restricted_indexes.is_empty() {
+        let mut state_index = 0;
+        columns[column_index].states.retain(|_| {
             state_index += 1;
-            state_len = columns[column_index].states.len();
+            !restricted_indexes.contains(&(state_index - 1))
+        });
+    }
+}
+
+/// Whether the given completed `state`
+/// violates the restrictions of its rule in the [Grammar],
+/// see [GrammarBuilder::not_followed_by()](crate::grammar::GrammarBuilder::not_followed_by())
+/// and [GrammarBuilder::reject()](crate::grammar::GrammarBuilder::reject()).
+fn restricted<AST>(
+    grammar: &Grammar<AST>,
+    columns: &[ParserColumn<AST>],
+    column_index: usize,
+    state: &ParserState<AST>,
+    successors: &[(usize, String)],
+    linear: bool,
+) -> bool {
+    let rule = match grammar.rules.get(&state.rule_name) {
+        Some(rule) => rule,
+        None => return false,
+    };
+
+    if let Some(char_class) = &rule.not_followed_by {
+        if successors.iter().any(|(_, kind)| char_class.matches(kind)) {
+            return true;
         }
     }
 
-    // println!();
-    // println!("Columns:");
-    // for (column_index, column) in columns.iter().enumerate() {
-    //     println!("  {column_index}");
-    //     for state in &column.states {
-    //         println!("    {state}");
-    //     }
-    // }
+    if linear && !rule.rejects.is_empty() {
+        let string: String = columns[state.start_column + 1..=column_index]
+            .iter()
+            .map(|column| column.kind.as_str())
+            .collect();
 
-    columns
+        if rule.rejects.contains(&string) {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Lex the `input` with the given [LexerRules]
+/// while parsing it with the given [Grammar],
+/// so that the [Lexer] knows what the parser expects.
+///
+/// Before producing each [Lexeme],
+/// the [Lexer] tries only the rules
+/// whose name is one of the kinds that the parser expects at that point,
+/// falling back to all the active rules if none of them matches.
+/// This solves ambiguities that otherwise need manual state juggling,
+/// like keywords that can also be used as identifiers,
+/// or a `/` that can be either a division or the start of a regular expression.
+///
+/// Return the [Lexeme]s and all possible Parse Trees.
+#[allow(clippy::type_complexity)]
+pub fn lex_and_parse<AST>(
+    lexer_rules: &LexerRules,
+    grammar: &Grammar<AST>,
+    input: &str,
+) -> Result<(Vec<Rc<Lexeme>>, Vec<Rc<Tree<AST>>>), LexAndParseError<AST>> {
+    let mut lexer = Lexer::new(lexer_rules, input);
+    let mut lexemes: Vec<Rc<Lexeme>> = Vec::new();
+    let mut columns = vec![ParserColumn {
+        index:  0,
+        kind:   '^'.to_string(),
+        states: Vec::new(),
+        unique: HashSet::new(),
+    }];
+
+    start(grammar, &mut columns[0]);
+
+    loop {
+        let column_index = columns.len() - 1;
+        process(grammar, &mut columns, column_index, &[], true);
+
+        lexer.expected_kinds = Some(
+            columns[column_index]
+                .states
+                .iter()
+                .filter(|state| {
+                    matches!(state.production.kind, ProductionKind::Lexemes)
+                })
+                .filter_map(|state| state.next_symbol().cloned())
+                .collect(),
+        );
+
+        let lexeme = match lexer.next() {
+            Some(lexeme) => Rc::new(lexeme.map_err(LexAndParseError::Lexer)?),
+            None => break,
+        };
+
+        columns.push(ParserColumn {
+            index:  column_index + 1,
+            kind:   lexeme.kind.clone(),
+            states: Vec::new(),
+            unique: HashSet::new(),
+        });
+        lexemes.push(lexeme);
+
+        for state_index in 0..columns[column_index].states.len() {
+            let state = &columns[column_index].states[state_index];
+
+            if let ProductionKind::Lexemes = state.production.kind {
+                if !state.completed()
+                    && state.production.matches(
+                        state.dot_index,
+                        &columns[column_index + 1].kind,
+                    )
+                {
+                    scan(
+                        &mut columns,
+                        column_index,
+                        state_index,
+                        column_index + 1,
+                    );
+                }
+            }
+        }
+
+        if columns[column_index + 1].states.is_empty() {
+            return Err(LexAndParseError::Parser(ParseError::unexpected(
+                grammar,
+                &lexemes,
+                &columns[column_index],
+            )));
+        }
+    }
+
+    lexer.attach_trailing_trivia(&mut lexemes);
+
+    let (columns, state) = keep_completed(grammar, &lexemes, columns)
+        .map_err(LexAndParseError::Parser)?;
+    let forest = Forest::new(grammar, &lexemes, &columns, &state)
+        .map_err(LexAndParseError::Parser)?;
+    let trees = forest.trees().collect();
+
+    Ok((lexemes, trees))
 }
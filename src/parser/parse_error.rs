@@ -2,17 +2,156 @@
 //
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::rc::Rc;
-
+use crate::grammar::Grammar;
+use crate::grammar::ProductionKind;
 use crate::lexer::Lexeme;
+use crate::parser::ParserColumn;
 use crate::parser::ParserState;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::rc::Rc;
 
 /// Internal representation of an error encountered by [crate::parser::parse()].
 pub struct ParseError<AST> {
     /// [Lexeme] where the error was found.
-    pub at:     Option<Rc<Lexeme>>,
-    /// Matched, partially matched, and expected lexemes up at this point.
-    pub states: Vec<ParserState<AST>>,
+    pub at:               Option<Rc<Lexeme>>,
+    /// [Lexeme] that could not be parsed,
+    /// or none if the input ended too early.
+    pub found:            Option<Rc<Lexeme>>,
+    /// Kinds of the [Lexeme]s that would have been accepted
+    /// instead of the found one, sorted.
+    pub expected_lexemes: Vec<String>,
+    /// Names of the rules that were being parsed
+    /// when the error was found, sorted.
+    pub expected_rules:   Vec<String>,
+    /// Human readable description of the error.
+    pub message:          String,
+    /// Matched, partially matched, and expected lexemes up at this point,
+    /// useful for debugging.
+    pub states:           Vec<ParserState<AST>>,
+}
+
+impl<AST> ParseError<AST> {
+    /// Explain that the lexeme after the `column`
+    /// is not accepted by any of its states.
+    pub(crate) fn unexpected(
+        grammar: &Grammar<AST>,
+        lexemes: &[Rc<Lexeme>],
+        column: &ParserColumn<AST>,
+    ) -> ParseError<AST> {
+        ParseError::unexpected_between(
+            grammar,
+            lexemes.get(column.index.overflowing_sub(1).0).cloned(),
+            lexemes.get(column.index).cloned(),
+            column,
+        )
+    }
+
+    /// Explain that the `found` lexeme after the `column`,
+    /// which ends with the lexeme `at`,
+    /// is not accepted by any of its states.
+    pub(crate) fn unexpected_between(
+        grammar: &Grammar<AST>,
+        at: Option<Rc<Lexeme>>,
+        found: Option<Rc<Lexeme>>,
+        column: &ParserColumn<AST>,
+    ) -> ParseError<AST> {
+        let mut expected_lexemes = BTreeSet::new();
+        let expected_rules: BTreeSet<String> = column
+            .states
+            .iter()
+            .filter(|state| {
+                matches!(state.production.kind, ProductionKind::Rules)
+            })
+            .filter_map(|state| state.next_symbol().cloned())
+            .collect();
+
+        // The parser only predicts the productions
+        // that can start with the found lexeme,
+        // so predict the rest of them here to know what was expected.
+        let mut predicted = column.clone();
+        let mut predicted_names = HashSet::new();
+        let mut state_index = 0;
+
+        while state_index < predicted.states.len() {
+            let state = predicted.states[state_index].clone();
+            state_index += 1;
+
+            match (state.next_symbol(), &state.production.kind) {
+                (Some(symbol), ProductionKind::Lexemes) => {
+                    expected_lexemes.insert(symbol.clone());
+                }
+                (Some(symbol), ProductionKind::Rules) => {
+                    if predicted_names.insert(symbol.clone()) {
+                        if let Some(rule) = grammar.rules.get(symbol) {
+                            for production in &rule.productions {
+                                predicted.add(ParserState {
+                                    rule_name:    rule.name.clone(),
+                                    production:   production.clone(),
+                                    dot_index:    0,
+                                    start_column: predicted.index,
+                                    end_column:   usize::MAX,
+                                });
+                            }
+                        }
+                    }
+
+                    // The rule may have been completed empty already.
+                    if predicted.states.iter().any(|completed| {
+                        completed.completed()
+                            && completed.start_column == predicted.index
+                            && *completed.rule_name == *symbol
+                    }) {
+                        predicted.add(state.advanced());
+                    }
+                }
+                (None, _) if state.start_column == predicted.index => {
+                    // An empty rule completes the states
+                    // that expect it in this same predicted.
+                    let parents: Vec<ParserState<AST>> = column
+                        .states
+                        .iter()
+                        .filter(|parent| {
+                            matches!(
+                                parent.production.kind,
+                                ProductionKind::Rules
+                            ) && parent.next_symbol() == Some(&state.rule_name)
+                        })
+                        .map(ParserState::advanced)
+                        .collect();
+
+                    for parent in parents {
+                        predicted.add(parent);
+                    }
+                }
+                (None, _) => {}
+            }
+        }
+
+        let mut message = match &found {
+            Some(found) => format!("Unexpected lexeme: {found}"),
+            None => "Unexpected end of input".to_string(),
+        };
+        if !expected_lexemes.is_empty() {
+            message.push_str(&format!(
+                "\nExpected one of: {}",
+                expected_lexemes
+                    .iter()
+                    .map(|kind| format!("{kind:?}"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+
+        ParseError {
+            at,
+            found,
+            expected_lexemes: expected_lexemes.into_iter().collect(),
+            expected_rules: expected_rules.into_iter().collect(),
+            message,
+            states: column.states.clone(),
+        }
+    }
 }
 
 impl<AST> std::fmt::Debug for ParseError<AST> {
@@ -23,20 +162,11 @@ impl<AST> std::fmt::Debug for ParseError<AST> {
 
 impl<AST> std::fmt::Display for ParseError<AST> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(at) = &self.at {
-            writeln!(f, "At: {}", at)?;
-        } else {
-            writeln!(f, "At start of the input")?;
-        }
+        writeln!(f, "{}", self.message)?;
 
-        write!(
-            f,
-            "States:\n  {}",
-            self.states
-                .iter()
-                .map(ParserState::to_string)
-                .collect::<Vec<String>>()
-                .join("\n  ")
-        )
+        match &self.at {
+            Some(at) => write!(f, "At: {at}"),
+            None => write!(f, "At start of the input"),
+        }
     }
 }
@@ -0,0 +1,39 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::parser::Forest;
+use crate::parser::Tree;
+use std::rc::Rc;
+
+/// Iterator that extracts Parse Trees from a [Forest] on demand.
+///
+/// Normally you create a [ParseTrees]
+/// by using [parse_iter()](crate::parser::parse_iter()).
+pub struct ParseTrees<AST> {
+    forest: Forest<AST>,
+    index:  usize,
+}
+
+impl<AST> Iterator for ParseTrees<AST> {
+    type Item = Rc<Tree<AST>>;
+
+    fn next(&mut self) -> Option<Rc<Tree<AST>>> {
+        let tree = self.forest.tree(self.index)?;
+        self.index += 1;
+
+        Some(tree)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.forest.count() - self.index;
+
+        (remaining, Some(remaining))
+    }
+}
+
+impl<AST> ParseTrees<AST> {
+    pub(crate) fn new(forest: Forest<AST>) -> ParseTrees<AST> {
+        ParseTrees { forest, index: 0 }
+    }
+}
@@ -17,6 +17,17 @@ pub struct ParserColumn<AST> {
     pub(crate) unique: HashSet<u64>,
 }
 
+impl<AST> std::clone::Clone for ParserColumn<AST> {
+    fn clone(&self) -> ParserColumn<AST> {
+        ParserColumn {
+            index:  self.index,
+            kind:   self.kind.clone(),
+            states: self.states.clone(),
+            unique: self.unique.clone(),
+        }
+    }
+}
+
 impl<AST> ParserColumn<AST> {
     pub(crate) fn add(&mut self, state: ParserState<AST>) {
         let mut state = state;
@@ -22,7 +22,7 @@ pub struct ParserState<AST> {
 
 impl<AST> std::fmt::Debug for ParserState<AST> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        write!(f, "{self}")
     }
 }
 
@@ -96,6 +96,17 @@ impl<AST> ParserState<AST> {
         self.production.symbols.get(self.dot_index)
     }
 
+    /// This state with the dot moved past its next symbol.
+    pub(crate) fn advanced(&self) -> ParserState<AST> {
+        ParserState {
+            rule_name:    self.rule_name.clone(),
+            production:   self.production.clone(),
+            dot_index:    self.dot_index + 1,
+            start_column: self.start_column,
+            end_column:   usize::MAX,
+        }
+    }
+
     pub(crate) fn hash_me(&self) -> u64 {
         let mut hasher = DefaultHasher::new();
         self.rule_name.hash(&mut hasher);
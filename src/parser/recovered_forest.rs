@@ -0,0 +1,185 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::lexer::Lexeme;
+use crate::parser::Forest;
+use crate::parser::Tree;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// [Lexeme] of a repaired input.
+#[derive(Clone)]
+pub(crate) struct Slot {
+    pub(crate) lexeme:   Rc<Lexeme>,
+    /// Index of this [Lexeme] in the input,
+    /// or of the [Lexeme] it was inserted before.
+    pub(crate) index:    usize,
+    /// Why this [Lexeme] was inserted,
+    /// or none if it comes from the input.
+    pub(crate) inserted: Option<String>,
+}
+
+/// [Forest] of an input repaired by
+/// [parse_with_recovery()](crate::parser::parse_with_recovery()).
+///
+/// The Parse Trees are extracted on demand,
+/// and the skipped and inserted [Lexeme]s
+/// are marked in them with [Tree::Error] nodes.
+pub struct RecoveredForest<AST> {
+    forest: Option<Forest<AST>>,
+    input:  Vec<Rc<Lexeme>>,
+    slots:  Vec<Slot>,
+    /// Errors that go right before each slot, or at the end.
+    errors: Vec<Vec<Rc<Tree<AST>>>>,
+}
+
+impl<AST> RecoveredForest<AST> {
+    /// Create a [RecoveredForest]
+    /// from the [Forest] of the repaired input, if it could be repaired,
+    /// and the indexes of the input [Lexeme]s that were skipped, and why.
+    pub(crate) fn new(
+        forest: Option<Forest<AST>>,
+        input: &[Rc<Lexeme>],
+        slots: Vec<Slot>,
+        skipped: &[(Range<usize>, String)],
+    ) -> RecoveredForest<AST> {
+        let mut errors: Vec<Vec<Rc<Tree<AST>>>> =
+            vec![Vec::new(); slots.len() + 1];
+
+        for (range, message) in skipped {
+            let lexemes = input[range.clone()].to_vec();
+            let slot_index = slots
+                .iter()
+                .position(|slot| slot.index >= range.end)
+                .unwrap_or(slots.len());
+
+            errors[slot_index].push(Rc::new(Tree::Error {
+                byte_range: lexemes[0].position.byte_index
+                    ..lexemes[lexemes.len() - 1].position_end.byte_index,
+                lexemes,
+                message: message.clone(),
+                lexeme_range: range.clone(),
+            }));
+        }
+
+        for (slot_index, slot) in slots.iter().enumerate() {
+            if let Some(message) = &slot.inserted {
+                errors[slot_index].push(Rc::new(Tree::Error {
+                    lexemes:      Vec::new(),
+                    message:      message.clone(),
+                    lexeme_range: slot.index..slot.index,
+                    byte_range:   slot.lexeme.byte_range(),
+                }));
+            }
+        }
+
+        RecoveredForest { forest, input: input.to_vec(), slots, errors }
+    }
+
+    /// Number of Parse Trees of the repaired input,
+    /// which is zero if the input could not be repaired.
+    ///
+    /// Saturates at [usize::MAX].
+    pub fn count(&self) -> usize {
+        self.forest.as_ref().map_or(0, Forest::count)
+    }
+
+    /// Extract the Parse Tree at position `index`,
+    /// or none if `index` is not less than [RecoveredForest::count()].
+    pub fn tree(&self, index: usize) -> Option<Rc<Tree<AST>>> {
+        self.forest.as_ref().and_then(|forest| forest.tree(index)).map(|tree| {
+            self.tree_with_errors(&tree, &mut self.errors.clone(), &mut 0, true)
+        })
+    }
+
+    /// Lazily iterate the Parse Trees of the repaired input.
+    pub fn trees(&self) -> impl Iterator<Item = Rc<Tree<AST>>> + '_ {
+        (0..self.count()).filter_map(|index| self.tree(index))
+    }
+
+    /// Copy of `tree` with the `errors` before each slot
+    /// added to the outermost node that has the slot as a child,
+    /// and with ranges that refer to the indexes of the input [Lexeme]s.
+    fn tree_with_errors(
+        &self,
+        tree: &Rc<Tree<AST>>,
+        errors: &mut [Vec<Rc<Tree<AST>>>],
+        slot_index: &mut usize,
+        root: bool,
+    ) -> Rc<Tree<AST>> {
+        match &**tree {
+            Tree::Leaf(_) => {
+                *slot_index += 1;
+
+                tree.clone()
+            }
+            Tree::Node {
+                rule_name,
+                production,
+                leaves,
+                lexeme_range,
+                byte_range,
+            } => {
+                let mut new_leaves = Vec::new();
+
+                for leaf in leaves {
+                    new_leaves.append(&mut errors[*slot_index]);
+                    new_leaves.push(
+                        self.tree_with_errors(leaf, errors, slot_index, false),
+                    );
+                }
+
+                if root {
+                    new_leaves.append(&mut errors[self.slots.len()]);
+                }
+
+                let start = self
+                    .slots
+                    .get(lexeme_range.start)
+                    .map_or(self.input.len(), |slot| slot.index);
+                let end = if lexeme_range.is_empty() {
+                    start
+                } else {
+                    let slot = &self.slots[lexeme_range.end - 1];
+
+                    match slot.inserted {
+                        Some(_) => slot.index,
+                        None => slot.index + 1,
+                    }
+                };
+                let mut lexeme_range = start..end;
+                let mut byte_range = byte_range.clone();
+
+                // Errors can be before any child, and after the last one
+                // of the root, so the node covers all of them.
+                for leaf in &new_leaves {
+                    if let Tree::Error {
+                        lexeme_range: error_lexeme_range,
+                        byte_range: error_byte_range,
+                        ..
+                    } = &**leaf
+                    {
+                        lexeme_range.start =
+                            lexeme_range.start.min(error_lexeme_range.start);
+                        lexeme_range.end =
+                            lexeme_range.end.max(error_lexeme_range.end);
+                        byte_range.start =
+                            byte_range.start.min(error_byte_range.start);
+                        byte_range.end =
+                            byte_range.end.max(error_byte_range.end);
+                    }
+                }
+
+                Rc::new(Tree::Node {
+                    rule_name: rule_name.clone(),
+                    production: production.clone(),
+                    leaves: new_leaves,
+                    lexeme_range,
+                    byte_range,
+                })
+            }
+            Tree::Error { .. } => tree.clone(),
+        }
+    }
+}
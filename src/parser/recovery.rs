@@ -0,0 +1,405 @@
+// SPDX-FileCopyrightText: 2022 Kevin Amado <kamadorueda@gmail.com>
+//
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::grammar::Grammar;
+use crate::lexer::Lexeme;
+use crate::lexer::Position;
+use crate::parser::parse::accepted;
+use crate::parser::parse::earley_from;
+use crate::parser::parse::keep_completed;
+use crate::parser::parse::rejected;
+use crate::parser::recovered_forest::Slot;
+use crate::parser::Forest;
+use crate::parser::ParseError;
+use crate::parser::ParserColumn;
+use crate::parser::RecoveredForest;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Number of [Lexeme]s after an error
+/// that a repair must allow the parser to consume
+/// in order to be accepted.
+const CONFIRMATION_LEXEMES: usize = 3;
+
+/// Maximum number of [Lexeme]s
+/// that can be inserted at the end of the input.
+const MAX_INSERTIONS_AT_END: usize = 3;
+
+/// Parse the provided [Lexeme]s with the given [Grammar],
+/// recovering from the errors found in the input.
+///
+/// Every time the parser finds an error,
+/// the input is repaired by trying, in order:
+///
+/// - Skipping or inserting a single [Lexeme],
+///   keeping the repair that lets the parser go further,
+///   as long as it can consume a few more [Lexeme]s after the error.
+/// - Skipping [Lexeme]s until one whose kind is in `sync_kinds`,
+///   and then skipping it too if needed.
+/// - Skipping a single [Lexeme]
+///   or, at the end of the input, inserting a few missing ones.
+///
+/// Every repair resumes the Earley algorithm
+/// from the columns before the error,
+/// so the input before it is parsed only once.
+///
+/// Return a [RecoveredForest] with all possible Parse Trees
+/// of the repaired input,
+/// where skipped and inserted [Lexeme]s are marked with
+/// [Tree::Error](crate::parser::Tree::Error) nodes,
+/// together with the errors that were found.
+/// The [RecoveredForest] is empty if the input could not be repaired.
+///
+/// Inserted [Lexeme]s have the expected kind and an empty raw content.
+pub fn parse_with_recovery<AST>(
+    grammar: &Grammar<AST>,
+    lexemes: &[Rc<Lexeme>],
+    sync_kinds: &[&str],
+) -> (RecoveredForest<AST>, Vec<ParseError<AST>>) {
+    let mut recovery = Recovery {
+        input:   lexemes,
+        slots:   Vec::new(),
+        columns: Vec::new(),
+        skipped: Vec::new(),
+        errors:  Vec::new(),
+    };
+
+    let candidate = Candidate {
+        slots:   lexemes
+            .iter()
+            .enumerate()
+            .map(|(index, lexeme)| Slot {
+                lexeme: lexeme.clone(),
+                index,
+                inserted: None,
+            })
+            .collect(),
+        skipped: None,
+        first:   0,
+    };
+    let attempt = recovery.attempt(grammar, &candidate);
+    let mut attempt = recovery.accept(candidate, attempt);
+
+    // Every repair skips or consumes at least one Lexeme of the input,
+    // except for the ones at the end of the input.
+    for _ in 0..=2 * lexemes.len() + 1 {
+        let (index, error) = match attempt {
+            Attempt::Accepted(forest) => {
+                return (recovery.finish(Some(forest)), recovery.errors);
+            }
+            Attempt::Failed { columns, index, error } => {
+                recovery.columns = columns;
+                (index, error)
+            }
+        };
+
+        let repair = recovery.repair(grammar, index, &error, sync_kinds);
+        recovery.errors.push(error);
+
+        match repair {
+            Some(repaired) => attempt = repaired,
+            None => break,
+        }
+    }
+
+    (recovery.finish(None), recovery.errors)
+}
+
+struct Recovery<'a, AST> {
+    input:   &'a [Rc<Lexeme>],
+    slots:   Vec<Slot>,
+    /// Columns of the Earley algorithm over the [Lexeme]s of the `slots`.
+    columns: Vec<ParserColumn<AST>>,
+    /// Indexes of the input [Lexeme]s that were skipped,
+    /// and why.
+    skipped: Vec<(Range<usize>, String)>,
+    errors:  Vec<ParseError<AST>>,
+}
+
+/// Repaired [Slot]s.
+struct Candidate {
+    slots:   Vec<Slot>,
+    /// Indexes of the input [Lexeme]s that this repair skips, and why.
+    skipped: Option<(Range<usize>, String)>,
+    /// Index of the first slot that differs from the current ones.
+    first:   usize,
+}
+
+/// Result of parsing the [Lexeme]s of a [Candidate].
+enum Attempt<AST> {
+    Accepted(Forest<AST>),
+    Failed {
+        columns: Vec<ParserColumn<AST>>,
+        /// Index of the slot where the parser failed.
+        index:   usize,
+        error:   ParseError<AST>,
+    },
+}
+
+impl<'a, AST> Recovery<'a, AST> {
+    /// [Attempt] of the repair that fixes the `error`
+    /// found at the slot `index`,
+    /// or none if the input cannot be repaired.
+    fn repair(
+        &mut self,
+        grammar: &Grammar<AST>,
+        index: usize,
+        error: &ParseError<AST>,
+        sync_kinds: &[&str],
+    ) -> Option<Attempt<AST>> {
+        if index == self.slots.len() {
+            return self.insert_at_end(grammar, error);
+        }
+
+        let mut candidates = Vec::new();
+        if self.slots[index].inserted.is_none() {
+            candidates.push(self.skip(index..index + 1, &error.message));
+        }
+        for kind in &error.expected_lexemes {
+            candidates.push(self.insert(
+                index,
+                std::slice::from_ref(kind),
+                &error.message,
+            ));
+        }
+
+        let mut attempts: Vec<Attempt<AST>> = candidates
+            .iter()
+            .map(|candidate| self.attempt(grammar, candidate))
+            .collect();
+        let progresses: Vec<usize> = candidates
+            .iter()
+            .zip(&attempts)
+            .map(|(candidate, attempt)| self.progress(candidate, attempt))
+            .collect();
+        let confirmation = (self.slots[index].index + CONFIRMATION_LEXEMES)
+            .min(self.input.len());
+
+        // The first of the repairs that go further.
+        let best = (0..candidates.len())
+            .rev()
+            .max_by_key(|candidate| progresses[*candidate]);
+
+        if let Some(best) = best {
+            if progresses[best] >= confirmation {
+                return Some(self.accept(
+                    candidates.swap_remove(best),
+                    attempts.swap_remove(best),
+                ));
+            }
+        }
+
+        for sync_index in index..self.slots.len() {
+            if !sync_kinds
+                .contains(&self.slots[sync_index].lexeme.kind.as_str())
+            {
+                continue;
+            }
+
+            for end in [sync_index, sync_index + 1] {
+                if end == index {
+                    continue;
+                }
+
+                let candidate = self.skip(index..end, &error.message);
+                let attempt = self.attempt(grammar, &candidate);
+                let resumed = self
+                    .slots
+                    .get(end)
+                    .map_or(self.input.len(), |slot| slot.index);
+                let confirmation =
+                    (resumed + CONFIRMATION_LEXEMES).min(self.input.len());
+
+                if self.progress(&candidate, &attempt) >= confirmation {
+                    return Some(self.accept(candidate, attempt));
+                }
+            }
+        }
+
+        match best {
+            Some(best) if progresses[best] > self.slots[index].index => {
+                Some(self.accept(
+                    candidates.swap_remove(best),
+                    attempts.swap_remove(best),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// [Attempt] of the repair that completes the input
+    /// by inserting a few [Lexeme]s at the end,
+    /// or none if the input cannot be completed.
+    fn insert_at_end(
+        &mut self,
+        grammar: &Grammar<AST>,
+        error: &ParseError<AST>,
+    ) -> Option<Attempt<AST>> {
+        let index = self.slots.len();
+        let mut pending: Vec<(Vec<String>, Vec<String>)> =
+            vec![(Vec::new(), error.expected_lexemes.clone())];
+
+        for _ in 0..MAX_INSERTIONS_AT_END {
+            let mut next_pending = Vec::new();
+
+            for (kinds, expected_lexemes) in &pending {
+                for kind in expected_lexemes {
+                    let mut kinds = kinds.clone();
+                    kinds.push(kind.clone());
+
+                    let candidate = self.insert(index, &kinds, &error.message);
+
+                    match self.attempt(grammar, &candidate) {
+                        Attempt::Accepted(forest) => {
+                            return Some(
+                                self.accept(
+                                    candidate,
+                                    Attempt::Accepted(forest),
+                                ),
+                            );
+                        }
+                        Attempt::Failed { error, .. } => {
+                            next_pending.push((kinds, error.expected_lexemes));
+                        }
+                    }
+                }
+            }
+
+            pending = next_pending;
+        }
+
+        None
+    }
+
+    /// [Candidate] without the slots in `range`.
+    fn skip(&self, range: Range<usize>, message: &str) -> Candidate {
+        let first = range.start;
+        let mut slots = self.slots.clone();
+        let skipped: Vec<Slot> =
+            slots.drain(range).filter(|slot| slot.inserted.is_none()).collect();
+
+        let skipped = match (skipped.first(), skipped.last()) {
+            (Some(first), Some(last)) => {
+                Some((first.index..last.index + 1, message.to_string()))
+            }
+            _ => None,
+        };
+
+        Candidate { slots, skipped, first }
+    }
+
+    /// [Candidate] with [Lexeme]s of the given `kinds` inserted at `index`.
+    fn insert(
+        &self,
+        index: usize,
+        kinds: &[String],
+        message: &str,
+    ) -> Candidate {
+        let (input_index, position) = match self.slots.get(index) {
+            Some(slot) => (slot.index, slot.lexeme.position.clone()),
+            None => (
+                self.input.len(),
+                self.slots
+                    .last()
+                    .map(|slot| slot.lexeme.position_end.clone())
+                    .unwrap_or(Position {
+                        column:     1,
+                        line:       1,
+                        byte_index: 0,
+                    }),
+            ),
+        };
+
+        let mut slots = self.slots.clone();
+        slots.splice(
+            index..index,
+            kinds.iter().map(|kind| Slot {
+                lexeme:   Rc::new(Lexeme {
+                    kind:            kind.clone(),
+                    raw:             String::new(),
+                    position:        position.clone(),
+                    position_end:    position.clone(),
+                    leading_trivia:  Vec::new(),
+                    trailing_trivia: Vec::new(),
+                }),
+                index:    input_index,
+                inserted: Some(message.to_string()),
+            }),
+        );
+
+        Candidate { slots, skipped: None, first: index }
+    }
+
+    fn accept(
+        &mut self,
+        candidate: Candidate,
+        attempt: Attempt<AST>,
+    ) -> Attempt<AST> {
+        self.skipped.extend(candidate.skipped);
+        self.slots = candidate.slots;
+
+        attempt
+    }
+
+    /// Parse the [Lexeme]s of the `candidate`,
+    /// resuming from the columns of the current slots
+    /// before the first one that differs.
+    fn attempt(
+        &self,
+        grammar: &Grammar<AST>,
+        candidate: &Candidate,
+    ) -> Attempt<AST> {
+        let lexemes: Vec<Rc<Lexeme>> =
+            candidate.slots.iter().map(|slot| slot.lexeme.clone()).collect();
+        let columns =
+            earley_from(grammar, &lexemes, &self.columns, candidate.first);
+
+        let error = match accepted(&columns) {
+            Some(_) => {
+                let forest = keep_completed(grammar, &lexemes, columns.clone())
+                    .and_then(|(completed, state)| {
+                        Forest::new(grammar, &lexemes, &completed, &state)
+                    });
+
+                match forest {
+                    Ok(forest) => return Attempt::Accepted(forest),
+                    Err(error) => error,
+                }
+            }
+            None => rejected(grammar, &lexemes, &columns),
+        };
+
+        let index = error
+            .found
+            .as_ref()
+            .and_then(|found| {
+                lexemes.iter().position(|lexeme| Rc::ptr_eq(lexeme, found))
+            })
+            .unwrap_or(lexemes.len());
+
+        Attempt::Failed { columns, index, error }
+    }
+
+    /// Index of the input [Lexeme] where the parser fails
+    /// after repairing it with the `candidate`,
+    /// or [usize::MAX] if it succeeds.
+    fn progress(&self, candidate: &Candidate, attempt: &Attempt<AST>) -> usize {
+        match attempt {
+            Attempt::Accepted(_) => usize::MAX,
+            Attempt::Failed { index, .. } => candidate
+                .slots
+                .get(*index)
+                .map_or(self.input.len(), |slot| slot.index),
+        }
+    }
+
+    fn finish(&mut self, forest: Option<Forest<AST>>) -> RecoveredForest<AST> {
+        RecoveredForest::new(
+            forest,
+            self.input,
+            std::mem::take(&mut self.slots),
+            &self.skipped,
+        )
+    }
+}
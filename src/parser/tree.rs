@@ -3,15 +3,17 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::grammar::Associativity;
+use crate::grammar::Disambiguation;
 use crate::grammar::Grammar;
 use crate::grammar::Production;
 use crate::grammar::ProductionAction;
 use crate::grammar::ProductionKind;
 use crate::lexer::Lexeme;
-use crate::parser::ParserColumn;
+use crate::parser::Cst;
+use crate::parser::ParseError;
 use crate::parser::ParserState;
-use std::collections::HashMap;
 use std::collections::LinkedList;
+use std::ops::Range;
 use std::rc::Rc;
 
 /// Representation of a Parse Tree,
@@ -22,11 +24,36 @@ pub enum Tree<AST> {
     /// Group of many [Tree::Leaf].
     Node {
         /// Name of the [GrammarRule](crate::grammar::GrammarRule) that produced this node.
-        rule_name:  Rc<String>,
+        rule_name:    Rc<String>,
         /// Reference to the [Production] that produced this node.
-        production: Rc<Production<AST>>,
+        production:   Rc<Production<AST>>,
         /// Children of this Node.
-        leaves:     Vec<Rc<Tree<AST>>>,
+        leaves:       Vec<Rc<Tree<AST>>>,
+        /// Indexes of the input [Lexeme]s covered by this Node,
+        /// as given by the columns of the Earley algorithm.
+        ///
+        /// The range is empty if this Node was produced by an empty production.
+        lexeme_range: Range<usize>,
+        /// Range of bytes of the input covered by this Node.
+        ///
+        /// The range is empty if this Node was produced by an empty production,
+        /// in which case it starts where the next [Lexeme] would start.
+        byte_range:   Range<usize>,
+    },
+    /// Part of the input that could not be parsed,
+    /// produced by [parse_with_recovery()](crate::parser::parse_with_recovery()).
+    ///
+    /// It contains the [Lexeme]s that were skipped,
+    /// or none if a missing [Lexeme] was inserted right after it.
+    Error {
+        /// Skipped [Lexeme]s.
+        lexemes:      Vec<Rc<Lexeme>>,
+        /// Human readable description of the error.
+        message:      String,
+        /// Indexes of the input [Lexeme]s covered by this Error.
+        lexeme_range: Range<usize>,
+        /// Range of bytes of the input covered by this Error.
+        byte_range:   Range<usize>,
     },
 }
 
@@ -47,9 +74,15 @@ impl<AST> std::fmt::Display for Tree<AST> {
                 Tree::Leaf(lexeme) => {
                     writeln!(f, "{indent}{lexeme}")?;
                 }
-                Tree::Node { rule_name, production, .. } => {
+                Tree::Node { rule_name, production, leaves, .. } => {
                     writeln!(f, "{indent}{rule_name} := {production}")?;
-                    stack.push_back(production.symbols.len());
+                    stack.push_back(leaves.len());
+                }
+                Tree::Error { lexemes, message, .. } => {
+                    writeln!(f, "{indent}Error {message:?}")?;
+                    for lexeme in lexemes {
+                        writeln!(f, "{indent}  {lexeme}")?;
+                    }
                 }
             }
 
@@ -68,8 +101,29 @@ impl<AST> std::fmt::Display for Tree<AST> {
 }
 
 impl<AST> Tree<AST> {
+    /// Range of bytes of the input covered by this Tree.
+    pub fn byte_range(&self) -> Range<usize> {
+        match self {
+            Tree::Leaf(lexeme) => lexeme.byte_range(),
+            Tree::Node { byte_range, .. } => byte_range.clone(),
+            Tree::Error { byte_range, .. } => byte_range.clone(),
+        }
+    }
+
+    /// Indexes of the input [Lexeme]s covered by this Tree,
+    /// or none if this Tree is a [Tree::Leaf].
+    pub fn lexeme_range(&self) -> Option<Range<usize>> {
+        match self {
+            Tree::Leaf(_) => None,
+            Tree::Node { lexeme_range, .. } => Some(lexeme_range.clone()),
+            Tree::Error { lexeme_range, .. } => Some(lexeme_range.clone()),
+        }
+    }
+
     /// Evaluate this Tree according to the [ProductionAction]s
     /// defined in the [Grammar].
+    ///
+    /// [Tree::Error] nodes are not evaluated.
     pub fn as_abstract_syntax_tree(&self) -> AST {
         let mut values: LinkedList<AST> = LinkedList::new();
         let mut lexemes: LinkedList<&Rc<Lexeme>> = LinkedList::new();
@@ -99,12 +153,20 @@ impl<AST> Tree<AST> {
                         }
                     };
                 }
+                Tree::Error { .. } => {}
             }
         }
 
         values.pop_front().unwrap()
     }
 
+    /// Lossless view of this Tree,
+    /// whose text is exactly the input that produced it,
+    /// see [Cst].
+    pub fn cst(&self) -> Cst<'_> {
+        Cst::new(self)
+    }
+
     /// Traverse the tree in post-order.
     ///
     /// - Recursively traverse the current node's Nth subtree.
@@ -153,220 +215,174 @@ impl<AST> Tree<AST> {
     }
 }
 
-pub(crate) fn build<AST>(
-    grammar: &Grammar<AST>,
-    lexemes: &[Rc<Lexeme>],
-    columns: &[ParserColumn<AST>],
-    state: &ParserState<AST>,
-) -> Vec<Rc<Tree<AST>>> {
-    let mut cache: HashMap<u64, Rc<Vec<Rc<Tree<AST>>>>> = HashMap::new();
-
-    for column in columns.iter() {
-        for state_partial in &column.states {
-            build_parse_trees(
-                &mut cache,
-                grammar,
-                lexemes,
-                columns,
-                state_partial,
-            );
-        }
-    }
-
-    (*cache.remove(&state.hash_me()).unwrap()).clone()
-}
-
-fn build_parse_trees<AST>(
-    cache: &mut HashMap<u64, Rc<Vec<Rc<Tree<AST>>>>>,
-    grammar: &Grammar<AST>,
-    lexemes: &[Rc<Lexeme>],
-    columns: &[ParserColumn<AST>],
-    state: &ParserState<AST>,
-) -> Rc<Vec<Rc<Tree<AST>>>> {
-    let key = state.hash_me();
-    match cache.get(&key) {
-        Some(parse_trees) => parse_trees.clone(),
-        None => {
-            let parse_trees = Rc::new(build_parse_trees_helper(
-                cache,
-                grammar,
-                lexemes,
-                columns,
-                vec![],
-                state,
-                state.production.symbols.len().overflowing_sub(1).0,
-                state.end_column,
-            ));
-
-            cache.insert(key, parse_trees.clone());
-
-            parse_trees
-        }
-    }
+/// Operand that was rejected because it chains
+/// a non-associative operator with another one of the same precedence.
+pub(crate) struct NonAssociativeChain<AST> {
+    pub(crate) operand: ParserState<AST>,
+    pub(crate) parent:  ParserState<AST>,
 }
 
-#[allow(clippy::too_many_arguments)]
-fn build_parse_trees_helper<AST>(
-    cache: &mut HashMap<u64, Rc<Vec<Rc<Tree<AST>>>>>,
-    grammar: &Grammar<AST>,
-    lexemes: &[Rc<Lexeme>],
-    columns: &[ParserColumn<AST>],
-
-    leaves: Vec<Rc<Tree<AST>>>,
-    state: &ParserState<AST>,
-    symbol_index: usize,
-    end_column: usize,
-) -> Vec<Rc<Tree<AST>>> {
-    if symbol_index == usize::MAX {
-        return vec![Rc::new(Tree::Node {
-            rule_name: state.rule_name.clone(),
-            production: state.production.clone(),
-            leaves,
-        })];
+impl<AST> NonAssociativeChain<AST> {
+    /// Explain the outermost of the `chains`,
+    /// preferring operands on the left.
+    pub(crate) fn outermost_parse_error(
+        chains: Vec<NonAssociativeChain<AST>>,
+        grammar: &Grammar<AST>,
+        lexemes: &[Rc<Lexeme>],
+    ) -> Option<ParseError<AST>> {
+        chains
+            .into_iter()
+            .min_by_key(|chain| {
+                (
+                    chain.parent.start_column,
+                    std::cmp::Reverse(chain.parent.end_column),
+                    chain.operand.start_column,
+                )
+            })
+            .map(|chain| chain.into_parse_error(grammar, lexemes))
     }
 
-    match &state.production.kind {
-        ProductionKind::Lexemes => {
-            let lexeme = &lexemes[end_column - 1];
-            let mut leaves = leaves;
-            let mut leaves_extended = vec![Rc::new(Tree::Leaf(lexeme.clone()))];
-            leaves_extended.append(&mut leaves);
-
-            build_parse_trees_helper(
-                cache,
-                grammar,
-                lexemes,
-                columns,
-                leaves_extended,
-                state,
-                symbol_index.overflowing_sub(1).0,
-                state.end_column - 1,
-            )
-        }
-        ProductionKind::Rules => {
-            let rule_name = &state.production.symbols[symbol_index];
-            let mut parse_trees = Vec::new();
-
-            for state_partial in columns[end_column]
-                .states
-                .iter()
-                .take_while(|state_partial| *state_partial != state)
-                .filter(|state_partial| {
-                    *state_partial.rule_name == *rule_name
-                        && (symbol_index > 0
-                            || state_partial.start_column == state.start_column)
-                        && satisfies_disambiguation(
-                            grammar,
-                            state_partial,
-                            state,
-                        )
-                })
-            {
-                for alternative in (*build_parse_trees(
-                    cache,
-                    grammar,
-                    lexemes,
-                    columns,
-                    state_partial,
-                ))
-                .clone()
-                {
-                    let mut leaves_extended = vec![alternative];
-                    leaves_extended.append(&mut leaves.clone());
-
-                    parse_trees.append(&mut build_parse_trees_helper(
-                        cache,
-                        grammar,
-                        lexemes,
-                        columns,
-                        leaves_extended,
-                        state,
-                        symbol_index.overflowing_sub(1).0,
-                        state_partial.start_column,
-                    ));
-                }
-            }
-
-            parse_trees
+    fn into_parse_error(
+        self,
+        grammar: &Grammar<AST>,
+        lexemes: &[Rc<Lexeme>],
+    ) -> ParseError<AST> {
+        let operator = get_disambiguation(grammar, &self.parent)
+            .map(|(index, _)| self.parent.production.symbols[index].clone())
+            .unwrap_or_default();
+        let operand_operator = get_disambiguation(grammar, &self.operand)
+            .map(|(index, _)| self.operand.production.symbols[index].clone())
+            .unwrap_or_default();
+
+        // The operator of the parent is next to the operand.
+        let at = if self.operand.start_column == self.parent.start_column {
+            lexemes.get(self.operand.end_column)
+        } else {
+            lexemes.get(self.operand.start_column.overflowing_sub(1).0)
+        };
+
+        let chain = lexemes[self.parent.start_column..self.parent.end_column]
+            .iter()
+            .map(|lexeme| lexeme.raw.as_str())
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        ParseError {
+            at:               at.cloned(),
+            found:            at.cloned(),
+            expected_lexemes: Vec::new(),
+            expected_rules:   Vec::new(),
+            message:          format!(
+                "Error at rule: {operator}\nThis operator is \
+                 non-associative, so it cannot be chained with: \
+                 {operand_operator}\nIn: {chain}"
+            ),
+            states:           vec![self.operand, self.parent],
         }
     }
 }
 
-fn satisfies_disambiguation<AST>(
+/// Whether an operand satisfies the disambiguation of its parent.
+pub(crate) enum Disambiguated {
+    Yes,
+    No,
+    /// The operand chains a non-associative operator
+    /// with another one of the same precedence.
+    NonAssociative,
+}
+
+pub(crate) fn satisfies_disambiguation<AST>(
     grammar: &Grammar<AST>,
     state_partial: &ParserState<AST>,
     state: &ParserState<AST>,
-) -> bool {
-    if let (Some(partial_index), Some(index)) = (
+) -> Disambiguated {
+    if let (
+        Some((partial_index, disambiguation_partial)),
+        Some((_, disambiguation)),
+    ) = (
         get_disambiguation(grammar, state_partial),
         get_disambiguation(grammar, state),
     ) {
-        if let (ProductionKind::Rules, ProductionKind::Rules) =
-            (&state_partial.production.kind, &state.production.kind)
-        {
-            let name_partial = &state_partial.production.symbols[partial_index];
-            let name = &state.production.symbols[index];
+        if disambiguation_partial.precedence < disambiguation.precedence {
+            return Disambiguated::No;
+        }
 
-            if let (Some(rule_partial), Some(rule)) =
-                (grammar.rules.get(name_partial), grammar.rules.get(name))
+        if disambiguation_partial.precedence == disambiguation.precedence {
+            if state_partial.end_column == state.end_column
+                && matches!(
+                    disambiguation_partial.associativity,
+                    Associativity::Left
+                )
             {
-                if let (Some(disambiguation_partial), Some(disambiguation)) =
-                    (&rule_partial.disambiguation, &rule.disambiguation)
-                {
-                    if disambiguation_partial.precedence
-                        < disambiguation.precedence
-                    {
-                        return false;
-                    }
+                return Disambiguated::No;
+            }
 
-                    if disambiguation_partial.precedence
-                        == disambiguation.precedence
-                    {
-                        if state_partial.end_column == state.end_column
-                            && matches!(
-                                disambiguation_partial.associativity,
-                                Associativity::Left
-                            )
-                        {
-                            return false;
-                        }
+            if state_partial.start_column == state.start_column
+                && matches!(disambiguation.associativity, Associativity::Right)
+            {
+                return Disambiguated::No;
+            }
 
-                        if state_partial.start_column == state.start_column
-                            && matches!(
-                                disambiguation.associativity,
-                                Associativity::Right
-                            )
-                        {
-                            return false;
-                        }
-                    }
-                }
+            // Only operands that have an operator
+            // on the side of the parent operator form a chain:
+            // `x op (y op z)` or `(x op y) op z`,
+            // but not `op (op z)` or `(x op) op`.
+            let symbols_partial = state_partial.production.symbols.len();
+
+            if (state_partial.end_column == state.end_column
+                && partial_index > 0
+                || state_partial.start_column == state.start_column
+                    && partial_index + 1 < symbols_partial)
+                && (matches!(
+                    disambiguation_partial.associativity,
+                    Associativity::None
+                ) || matches!(
+                    disambiguation.associativity,
+                    Associativity::None
+                ))
+            {
+                return Disambiguated::NonAssociative;
             }
         }
     }
 
-    true
+    Disambiguated::Yes
 }
 
+/// Index of the operator symbol of the production of `state`
+/// and its [Disambiguation],
+/// giving priority to the one given to the production itself.
 fn get_disambiguation<AST>(
     grammar: &Grammar<AST>,
     state: &ParserState<AST>,
-) -> Option<usize> {
+) -> Option<(usize, Disambiguation)> {
     if let ProductionKind::Rules = state.production.kind {
-        let mut disambiguations =
-            state.production.symbols.iter().enumerate().filter_map(
-                |(index, symbol)| {
-                    if let Some(rule) = grammar.rules.get(symbol) {
-                        if rule.disambiguation.is_some() {
-                            return Some(index);
-                        }
-                    }
+        let symbols = &state.production.symbols;
+        let index = symbols.iter().position(|symbol| {
+            matches!(
+                grammar.rules.get(symbol),
+                Some(rule) if rule.disambiguation.is_some()
+            )
+        });
+
+        if let Some(disambiguation) = &*state.production.disambiguation.borrow()
+        {
+            let index = index
+                .or_else(|| {
+                    symbols
+                        .iter()
+                        .position(|symbol| *symbol != *state.rule_name)
+                })
+                .unwrap_or(0);
+
+            return Some((index, disambiguation.clone()));
+        }
 
-                    None
-                },
-            );
+        index.map(|index| {
+            let rule = &grammar.rules[&symbols[index]];
 
-        disambiguations.next()
+            (index, rule.disambiguation.clone().unwrap())
+        })
     } else {
         None
     }
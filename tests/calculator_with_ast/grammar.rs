@@ -1,6 +1,7 @@
 use santiago::grammar::Associativity;
 use santiago::grammar::Grammar;
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum AST {
     Int(isize),
@@ -0,0 +1,24 @@
+use santiago::grammar::Associativity;
+use santiago::grammar::Grammar;
+
+pub fn grammar() -> Grammar<()> {
+    santiago::grammar!(
+        "expr" => rules "int";
+
+        "expr" => rules "expr" "less" "expr";
+        "expr" => rules "expr" "greater" "expr";
+        "expr" => rules "expr" "add" "expr";
+        "expr" => rules "negate" "expr";
+
+        "int" => lexemes "INT";
+
+        "less" => lexemes "<";
+        "greater" => lexemes ">";
+        "add" => lexemes "+";
+        "negate" => lexemes "-";
+
+        Associativity::None => rules "less" "greater";
+        Associativity::Left => rules "add";
+        Associativity::None => rules "negate";
+    )
+}
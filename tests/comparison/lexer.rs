@@ -0,0 +1,12 @@
+use santiago::lexer::LexerRules;
+
+pub fn lexer_rules() -> LexerRules {
+    santiago::lexer_rules!(
+        "DEFAULT" | "INT" = pattern r"[0-9]+";
+        "DEFAULT" | "+" = string "+";
+        "DEFAULT" | "-" = string "-";
+        "DEFAULT" | "<" = string "<";
+        "DEFAULT" | ">" = string ">";
+        "DEFAULT" | "WS" = pattern r"\s" => |lexer| lexer.skip();
+    )
+}
@@ -0,0 +1,57 @@
+use santiago::grammar::Associativity;
+use santiago::grammar::Grammar;
+
+pub fn grammar() -> Grammar<()> {
+    santiago::grammar!(
+        "statements" => empty;
+        "statements" => rules "statements" "statement";
+
+        "statement" => rules "simple_statement" "NEWLINE";
+        "statement" => rules "DEF" "ID" "(" "ids" ")" ":" "block";
+        "statement" => rules "IF" "expr" ":" "block";
+        "statement" => rules "IF" "expr" ":" "block" "ELSE" ":" "block";
+
+        "block" => rules "NEWLINE" "INDENT" "statement" "statements" "DEDENT";
+
+        "simple_statement" => rules "ID" "=" "expr";
+        "simple_statement" => rules "RETURN" "expr";
+        "simple_statement" => rules "PASS";
+        "simple_statement" => rules "expr";
+
+        "expr" => rules "expr" "+" "expr";
+        "expr" => rules "ID" "(" "exprs" ")";
+        "expr" => rules "[" "exprs" "]";
+        "expr" => rules "(" "expr" ")";
+        "expr" => rules "ID";
+        "expr" => rules "INT";
+
+        "exprs" => empty;
+        "exprs" => rules "expr";
+        "exprs" => rules "expr" "," "exprs";
+
+        "ids" => empty;
+        "ids" => rules "ID";
+        "ids" => rules "ID" "," "ids";
+
+        "DEF" => lexemes "DEF";
+        "IF" => lexemes "IF";
+        "ELSE" => lexemes "ELSE";
+        "RETURN" => lexemes "RETURN";
+        "PASS" => lexemes "PASS";
+        "ID" => lexemes "ID";
+        "INT" => lexemes "INT";
+        ":" => lexemes ":";
+        "," => lexemes ",";
+        "=" => lexemes "=";
+        "+" => lexemes "+";
+        "(" => lexemes "(";
+        ")" => lexemes ")";
+        "[" => lexemes "[";
+        "]" => lexemes "]";
+        "NEWLINE" => lexemes "NEWLINE";
+        "INDENT" => lexemes "INDENT";
+        "DEDENT" => lexemes "DEDENT";
+
+        Associativity::Left => rules "+";
+    )
+}
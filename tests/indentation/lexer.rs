@@ -0,0 +1,26 @@
+use santiago::lexer::Indentation;
+use santiago::lexer::LexerBuilder;
+use santiago::lexer::LexerRules;
+
+pub fn lexer_rules() -> LexerRules {
+    LexerBuilder::new()
+        .string(&["DEFAULT"], "DEF", "def", |lexer| lexer.take())
+        .string(&["DEFAULT"], "IF", "if", |lexer| lexer.take())
+        .string(&["DEFAULT"], "ELSE", "else", |lexer| lexer.take())
+        .string(&["DEFAULT"], "RETURN", "return", |lexer| lexer.take())
+        .string(&["DEFAULT"], "PASS", "pass", |lexer| lexer.take())
+        .pattern(&["DEFAULT"], "ID", r"[a-z_]+", |lexer| lexer.take())
+        .pattern(&["DEFAULT"], "INT", r"[0-9]+", |lexer| lexer.take())
+        .string(&["DEFAULT"], ":", ":", |lexer| lexer.take())
+        .string(&["DEFAULT"], ",", ",", |lexer| lexer.take())
+        .string(&["DEFAULT"], "=", "=", |lexer| lexer.take())
+        .string(&["DEFAULT"], "+", "+", |lexer| lexer.take())
+        .string(&["DEFAULT"], "(", "(", |lexer| lexer.take())
+        .string(&["DEFAULT"], ")", ")", |lexer| lexer.take())
+        .string(&["DEFAULT"], "[", "[", |lexer| lexer.take())
+        .string(&["DEFAULT"], "]", "]", |lexer| lexer.take())
+        .pattern(&["DEFAULT"], "COMMENT", r"#[^\n]*", |lexer| lexer.skip())
+        .pattern(&["DEFAULT"], "WS", r"[ \t]+", |lexer| lexer.skip())
+        .indentation(&["DEFAULT"], Indentation::default())
+        .finish()
+}
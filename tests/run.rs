@@ -2,14 +2,19 @@
 //
 // SPDX-License-Identifier: GPL-3.0-only
 
+#![allow(clippy::upper_case_acronyms)]
+
 mod ambiguous_integer_addition;
 mod calculator;
 mod calculator_with_ast;
+mod comparison;
+mod indentation;
 mod integer_addition;
 mod integer_addition_with_ast;
 mod javascript_string_interpolation;
 mod nix;
 mod smallest;
+mod unary_minus;
 
 #[test]
 fn ambiguous_integer_addition() {
@@ -41,6 +46,140 @@ fn calculator_with_ast() {
     );
 }
 
+#[test]
+fn comparison() {
+    run(
+        "comparison",
+        &comparison::lexer::lexer_rules(),
+        &comparison::grammar::grammar(),
+        false,
+    );
+}
+
+#[test]
+fn comparison_chains() {
+    let lexer_rules = comparison::lexer::lexer_rules();
+    let grammar = comparison::grammar::grammar();
+
+    for (input, operator, operand_operator, at) in [
+        ("1 < 2 < 3", "less", "less", (1, 7)),
+        ("1 < 2 > 3", "greater", "less", (1, 7)),
+        ("1 + 2 > 3 + 4 < 5", "less", "greater", (1, 15)),
+    ] {
+        let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+        let error = santiago::parser::parse(&grammar, &lexemes).unwrap_err();
+
+        assert_eq!(
+            error.message,
+            format!(
+                "Error at rule: {operator}\nThis operator is \
+                 non-associative, so it cannot be chained with: \
+                 {operand_operator}\nIn: {input}"
+            )
+        );
+        let lexeme = error.at.unwrap();
+        assert_eq!((lexeme.position.line, lexeme.position.column), at);
+    }
+}
+
+#[test]
+fn indentation() {
+    run(
+        "indentation",
+        &indentation::lexer::lexer_rules(),
+        &indentation::grammar::grammar(),
+        false,
+    );
+}
+
+#[test]
+fn indentation_errors() {
+    use santiago::lexer::Indentation;
+    use santiago::lexer::LexerBuilder;
+    use santiago::lexer::Tabs;
+
+    let lexer_rules = indentation::lexer::lexer_rules();
+
+    let error =
+        santiago::lexer::lex(&lexer_rules, "if a:\n    b\n  c").unwrap_err();
+    assert_eq!(
+        error.message,
+        "Unindent does not match any outer indentation level"
+    );
+    assert_eq!((error.byte_index, error.match_len), (12, Some(2)));
+
+    let (lexemes, errors) =
+        santiago::lexer::lex_with_recovery(&lexer_rules, "if a:\n    b\n  c");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        lexemes.iter().map(|lexeme| lexeme.kind.as_str()).collect::<Vec<_>>(),
+        vec![
+            "IF", "ID", ":", "NEWLINE", "INDENT", "ID", "NEWLINE", "ERROR",
+            "ID", "NEWLINE", "DEDENT"
+        ]
+    );
+
+    let lexer_rules = LexerBuilder::new()
+        .pattern(&["DEFAULT"], "ID", r"[a-z]+", |lexer| lexer.take())
+        .string(&["DEFAULT"], "{", "{", |lexer| lexer.take())
+        .string(&["DEFAULT"], "}", "}", |lexer| lexer.take())
+        .pattern(&["DEFAULT"], "WS", r" +", |lexer| lexer.skip())
+        .indentation(
+            &["DEFAULT"],
+            Indentation {
+                indent:         "BEGIN".to_string(),
+                dedent:         "END".to_string(),
+                newline:        ";".to_string(),
+                tabs:           Tabs::Forbid,
+                open_brackets:  vec!["{".to_string()],
+                close_brackets: vec!["}".to_string()],
+                comments:       vec![],
+            },
+        )
+        .finish();
+
+    assert_eq!(
+        santiago::lexer::lex(&lexer_rules, "a\n\tb").unwrap_err().message,
+        "Tabs are not allowed in the indentation"
+    );
+    assert_eq!(
+        santiago::lexer::lex(&lexer_rules, "a\n b {\nc\n}\n\nd")
+            .unwrap()
+            .iter()
+            .map(|lexeme| lexeme.kind.as_str())
+            .collect::<Vec<_>>(),
+        vec!["ID", ";", "BEGIN", "ID", "{", "ID", "}", ";", "END", "ID", ";"]
+    );
+
+    // Brackets only count in the states that track the indentation.
+    let lexer_rules = LexerBuilder::new()
+        .pattern(&["DEFAULT"], "ID", r"[a-z]+", |lexer| lexer.take())
+        .string(&["DEFAULT"], "\"", "\"", |lexer| {
+            lexer.push_state("STRING");
+            lexer.take()
+        })
+        .string(&["STRING"], "\"", "\"", |lexer| {
+            lexer.pop_state();
+            lexer.take()
+        })
+        .string(&["STRING"], "(", "(", |lexer| lexer.take())
+        .pattern(&["DEFAULT"], "WS", r" +", |lexer| lexer.skip())
+        .indentation(&["DEFAULT"], Indentation::default())
+        .finish();
+
+    assert_eq!(
+        santiago::lexer::lex(&lexer_rules, "a \"(\"\n  b")
+            .unwrap()
+            .iter()
+            .map(|lexeme| lexeme.kind.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "ID", "\"", "(", "\"", "NEWLINE", "INDENT", "ID", "NEWLINE",
+            "DEDENT"
+        ]
+    );
+}
+
 #[test]
 fn javascript_string_interpolation() {
     run(
@@ -86,6 +225,1752 @@ fn smallest() {
     );
 }
 
+#[test]
+fn unary_minus() {
+    run(
+        "unary_minus",
+        &unary_minus::lexer::lexer_rules(),
+        &unary_minus::grammar::grammar(),
+        true,
+    );
+}
+
+#[test]
+fn parse_forest() {
+    let lexer_rules = ambiguous_integer_addition::lexer::lexer_rules();
+    let grammar = ambiguous_integer_addition::grammar::grammar();
+
+    // Number of ways to parenthesize `n + 1` operands.
+    let catalan = [1, 1, 2, 5, 14, 42, 132, 429];
+
+    for (operators, count) in catalan.into_iter().enumerate() {
+        let input = vec!["1"; operators + 1].join(" + ");
+        let lexemes = santiago::lexer::lex(&lexer_rules, &input).unwrap();
+        let forest =
+            santiago::parser::parse_forest(&grammar, &lexemes).unwrap();
+
+        assert_eq!(forest.count(), count);
+        assert!(forest.tree(count).is_none());
+
+        let parse_trees = santiago::parser::parse(&grammar, &lexemes).unwrap();
+        let forest_trees: Vec<String> =
+            forest.trees().map(|tree| tree.to_string()).collect();
+        assert_eq!(
+            forest_trees,
+            parse_trees
+                .iter()
+                .map(|tree| tree.to_string())
+                .collect::<Vec<String>>()
+        );
+        assert_eq!(
+            forest.tree(count - 1).unwrap().to_string(),
+            parse_trees[count - 1].to_string()
+        );
+    }
+
+    // Too many Parse Trees to build them all.
+    let input = ["1"; 30].join(" + ");
+    let lexemes = santiago::lexer::lex(&lexer_rules, &input).unwrap();
+    let forest = santiago::parser::parse_forest(&grammar, &lexemes).unwrap();
+    assert_eq!(forest.count(), 1_002_242_216_651_368);
+    assert!(forest.tree(forest.count() - 1).is_some());
+
+    let input = ["1"; 60].join(" + ");
+    let lexemes = santiago::lexer::lex(&lexer_rules, &input).unwrap();
+    let forest = santiago::parser::parse_forest(&grammar, &lexemes).unwrap();
+    assert_eq!(forest.count(), usize::MAX);
+
+    let lexer_rules = calculator::lexer::lexer_rules();
+    let grammar = calculator::grammar::grammar();
+    let lexemes =
+        santiago::lexer::lex(&lexer_rules, "1 + 2 * 3 - 4 / 5").unwrap();
+    let forest = santiago::parser::parse_forest(&grammar, &lexemes).unwrap();
+    assert_eq!(forest.count(), 1);
+    assert_eq!(
+        forest.tree(0).unwrap().to_string(),
+        santiago::parser::parse(&grammar, &lexemes).unwrap()[0].to_string()
+    );
+
+    let lexer_rules = comparison::lexer::lexer_rules();
+    let grammar = comparison::grammar::grammar();
+    let lexemes = santiago::lexer::lex(&lexer_rules, "1 < 2 < 3").unwrap();
+    assert_eq!(
+        santiago::parser::parse_forest(&grammar, &lexemes)
+            .err()
+            .unwrap()
+            .message,
+        santiago::parser::parse(&grammar, &lexemes).unwrap_err().message
+    );
+}
+
+#[test]
+fn parse_iter() {
+    let lexer_rules = ambiguous_integer_addition::lexer::lexer_rules();
+    let grammar = ambiguous_integer_addition::grammar::grammar();
+
+    let input = ["1"; 7].join(" + ");
+    let lexemes = santiago::lexer::lex(&lexer_rules, &input).unwrap();
+    let parse_trees: Vec<String> = santiago::parser::parse(&grammar, &lexemes)
+        .unwrap()
+        .iter()
+        .map(|tree| tree.to_string())
+        .collect();
+    let lazy_parse_trees: Vec<String> =
+        santiago::parser::parse_iter(&grammar, &lexemes)
+            .unwrap()
+            .map(|tree| tree.to_string())
+            .collect();
+    assert_eq!(parse_trees.len(), 132);
+    assert_eq!(lazy_parse_trees, parse_trees);
+
+    // Too many Parse Trees to build them all.
+    let input = ["1"; 60].join(" + ");
+    let lexemes = santiago::lexer::lex(&lexer_rules, &input).unwrap();
+    let first_parse_trees: Vec<_> =
+        santiago::parser::parse_iter(&grammar, &lexemes)
+            .unwrap()
+            .take(3)
+            .collect();
+    assert_eq!(first_parse_trees.len(), 3);
+    assert_eq!(first_parse_trees[0].byte_range(), 0..input.len());
+
+    let lexer_rules = comparison::lexer::lexer_rules();
+    let grammar = comparison::grammar::grammar();
+    let lexemes = santiago::lexer::lex(&lexer_rules, "1 < 2 < 3").unwrap();
+    assert_eq!(
+        santiago::parser::parse_iter(&grammar, &lexemes).err().unwrap().message,
+        santiago::parser::parse(&grammar, &lexemes).unwrap_err().message
+    );
+}
+
+#[test]
+fn parse_errors() {
+    let lexer_rules = calculator::lexer::lexer_rules();
+    let grammar = calculator::grammar::grammar();
+
+    for (input, found, expected_lexemes, expected_rules, message, at) in [
+        (
+            "1 + * 2",
+            Some("*"),
+            vec!["INT"],
+            vec!["expr"],
+            "Unexpected lexeme: * \"*\" (1, 5)\nExpected one of: \"INT\"",
+            "At: + \"+\" (1, 3)",
+        ),
+        (
+            "1 2",
+            Some("INT"),
+            vec!["*", "+", "-", "/"],
+            vec!["add", "divide", "multiply", "subtract"],
+            "Unexpected lexeme: INT \"2\" (1, 3)\nExpected one of: \"*\", \
+             \"+\", \"-\", \"/\"",
+            "At: INT \"1\" (1, 1)",
+        ),
+        (
+            "1 +",
+            None,
+            vec!["INT"],
+            vec!["expr", "int"],
+            "Unexpected end of input\nExpected one of: \"INT\"",
+            "At: + \"+\" (1, 3)",
+        ),
+        (
+            "",
+            None,
+            vec!["INT"],
+            vec!["expr", "int"],
+            "Unexpected end of input\nExpected one of: \"INT\"",
+            "At start of the input",
+        ),
+    ] {
+        let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+        let error = santiago::parser::parse(&grammar, &lexemes).unwrap_err();
+
+        assert_eq!(
+            error.found.as_ref().map(|found| found.kind.clone()).as_deref(),
+            found
+        );
+        assert_eq!(error.expected_lexemes, expected_lexemes);
+        assert_eq!(error.expected_rules, expected_rules);
+        assert_eq!(error.message, message);
+        assert_eq!(error.to_string(), format!("{message}\n{at}"));
+        assert!(!error.states.is_empty());
+    }
+
+    // Lexemes after an empty rule are expected too.
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "A" = string "a";
+        "DEFAULT" | "B" = string "b";
+        "DEFAULT" | "C" = string "c";
+    );
+    let grammar = santiago::grammar!(
+        "s" => rules "optional_a" "b";
+        "optional_a" => empty;
+        "optional_a" => lexemes "A";
+        "b" => lexemes "B";
+    );
+    let lexemes = santiago::lexer::lex(&lexer_rules, "c").unwrap();
+    let error = santiago::parser::parse(&grammar, &lexemes).unwrap_err();
+    assert_eq!(error.expected_lexemes, ["A", "B"]);
+}
+
+#[test]
+fn parse_with_recovery() {
+    fn errors<AST>(
+        parse_tree: &santiago::parser::Tree<AST>,
+    ) -> Vec<(std::ops::Range<usize>, String)> {
+        parse_tree
+            .traverse_in_pre_order()
+            .into_iter()
+            .filter_map(|tree| match tree {
+                santiago::parser::Tree::Error { lexemes, .. } => Some((
+                    tree.lexeme_range().unwrap(),
+                    lexemes
+                        .iter()
+                        .map(|lexeme| lexeme.raw.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(" "),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    let lexer_rules = calculator::lexer::lexer_rules();
+    let grammar = calculator::grammar::grammar();
+
+    for (input, sync_kinds, expected_errors, expected_error_nodes) in [
+        ("1 + 2", vec![], vec![], vec![]),
+        (
+            "1 + * 2",
+            vec![],
+            vec!["Unexpected lexeme: * \"*\" (1, 5)"],
+            vec![(2..3, "*")],
+        ),
+        (
+            "1 2",
+            vec![],
+            vec!["Unexpected lexeme: INT \"2\" (1, 3)"],
+            vec![(1..2, "2")],
+        ),
+        (
+            "* 1 +",
+            vec![],
+            vec![
+                "Unexpected lexeme: * \"*\" (1, 1)",
+                "Unexpected end of input",
+            ],
+            vec![(0..1, "*"), (3..3, "")],
+        ),
+        (
+            "1 + 2 3 4 5 + 6",
+            vec![],
+            vec![
+                "Unexpected lexeme: INT \"3\" (1, 7)",
+                "Unexpected lexeme: INT \"4\" (1, 9)",
+                "Unexpected lexeme: INT \"5\" (1, 11)",
+            ],
+            vec![(3..4, "3"), (4..5, "4"), (5..6, "5")],
+        ),
+        (
+            "1 + 2 3 4 5 + 6",
+            vec!["+"],
+            vec!["Unexpected lexeme: INT \"3\" (1, 7)"],
+            vec![(3..6, "3 4 5")],
+        ),
+    ] {
+        let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+        let (forest, parse_errors) = santiago::parser::parse_with_recovery(
+            &grammar,
+            &lexemes,
+            &sync_kinds,
+        );
+        let parse_trees: Vec<_> = forest.trees().collect();
+
+        assert_eq!(forest.count(), 1);
+        assert_eq!(parse_trees[0].lexeme_range(), Some(0..lexemes.len()));
+        assert_eq!(
+            parse_errors
+                .iter()
+                .map(|error| error.message.lines().next().unwrap())
+                .collect::<Vec<&str>>(),
+            expected_errors
+        );
+        assert_eq!(
+            errors(&parse_trees[0]),
+            expected_error_nodes
+                .into_iter()
+                .map(|(range, raw)| (range, raw.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        if parse_errors.is_empty() {
+            assert_eq!(
+                parse_trees[0].to_string(),
+                santiago::parser::parse(&grammar, &lexemes).unwrap()[0]
+                    .to_string()
+            );
+        }
+    }
+
+    // The error is in the middle of the leaves of a node that is not the root,
+    // and every node covers its children in order.
+    let lexemes =
+        santiago::lexer::lex(&lexer_rules, "1 * 2 3 * 4 + 5").unwrap();
+    let (forest, _) =
+        santiago::parser::parse_with_recovery(&grammar, &lexemes, &[]);
+    let parse_tree = forest.tree(0).unwrap();
+    assert_eq!(errors(&parse_tree), [(3..4, "3".to_string())]);
+    for tree in parse_tree.traverse_in_pre_order() {
+        if let santiago::parser::Tree::Node { leaves, lexeme_range, .. } = tree
+        {
+            let mut lexeme_index = lexeme_range.start;
+            let mut byte_index = tree.byte_range().start;
+
+            for leaf in leaves {
+                if let Some(leaf_lexeme_range) = leaf.lexeme_range() {
+                    assert!(lexeme_index <= leaf_lexeme_range.start);
+                    lexeme_index = leaf_lexeme_range.end;
+                }
+                assert!(byte_index <= leaf.byte_range().start);
+                byte_index = leaf.byte_range().end;
+            }
+
+            assert!(lexeme_index <= lexeme_range.end);
+            assert!(byte_index <= tree.byte_range().end);
+        }
+    }
+    assert!(parse_tree.traverse_in_pre_order().into_iter().any(|tree| {
+        matches!(
+            tree,
+            santiago::parser::Tree::Node { leaves, .. }
+            if leaves.len() == 4
+                && matches!(&*leaves[1], santiago::parser::Tree::Error { .. })
+        )
+    }));
+
+    let lexemes = santiago::lexer::lex(
+        &calculator_with_ast::lexer::lexer_rules(),
+        "1 + * 2",
+    )
+    .unwrap();
+    let (forest, _) = santiago::parser::parse_with_recovery(
+        &calculator_with_ast::grammar::grammar(),
+        &lexemes,
+        &[],
+    );
+    assert_eq!(
+        format!("{:?}", forest.tree(0).unwrap().as_abstract_syntax_tree()),
+        "BinaryOperation([Int(1), OperatorAdd, Int(2)])"
+    );
+
+    // Too many Parse Trees to build them all.
+    let input = ["1"; 30].join(" + ") + " +";
+    let lexemes = santiago::lexer::lex(
+        &ambiguous_integer_addition::lexer::lexer_rules(),
+        &input,
+    )
+    .unwrap();
+    let (forest, parse_errors) = santiago::parser::parse_with_recovery(
+        &ambiguous_integer_addition::grammar::grammar(),
+        &lexemes,
+        &[],
+    );
+    assert_eq!(parse_errors.len(), 1);
+    assert_eq!(forest.count(), 3_814_986_502_092_304);
+    assert_eq!(
+        forest.trees().next().unwrap().lexeme_range(),
+        Some(0..lexemes.len())
+    );
+}
+
+#[test]
+fn diagnostics() {
+    let lexer_rules = calculator::lexer::lexer_rules();
+    let grammar = calculator::grammar::grammar();
+
+    let error = santiago::lexer::lex(&lexer_rules, "1 + a").unwrap_err();
+    assert_eq!(
+        santiago::diagnostics::Diagnostic::from(&error).render(
+            "input.calc",
+            "1 + a",
+            false
+        ),
+        [
+            "error: Expecting one of the following 6 lexemes: \"INT\", \"+\", \
+             \"-\", \"*\", \"/\", \"WS\"",
+            " --> input.calc:1:5",
+            "  |",
+            "1 | 1 + a",
+            "  |     ^ no lexer rule matches this input",
+            "  = note: With states stack: [\"DEFAULT\"]",
+            "",
+        ]
+        .join("\n")
+    );
+
+    for (input, colors, report) in [
+        (
+            "1 + * 2",
+            false,
+            vec![
+                "error: Unexpected lexeme",
+                " --> input.calc:1:5",
+                "  |",
+                "1 | 1 + * 2",
+                "  |     ^ found \"*\"",
+                "  = note: Expected one of: \"INT\"",
+                "",
+            ],
+        ),
+        (
+            "1\n+\n",
+            false,
+            vec![
+                "error: Unexpected end of input",
+                " --> input.calc:2:2",
+                "  |",
+                "2 | +",
+                "  |  ^ the input ends here",
+                "  = note: Expected one of: \"INT\"",
+                "",
+            ],
+        ),
+        (
+            "1 + * 2",
+            true,
+            vec![
+                "\u{1b}[1;31merror\u{1b}[0m\u{1b}[1m: Unexpected \
+                 lexeme\u{1b}[0m",
+                " \u{1b}[1;34m-->\u{1b}[0m input.calc:1:5",
+                "  \u{1b}[1;34m|\u{1b}[0m",
+                "\u{1b}[1;34m1\u{1b}[0m \u{1b}[1;34m|\u{1b}[0m 1 + * 2",
+                "  \u{1b}[1;34m|\u{1b}[0m     \u{1b}[1;31m^\u{1b}[0m \
+                 \u{1b}[1;31mfound \"*\"\u{1b}[0m",
+                "  \u{1b}[1;34m=\u{1b}[0m note: Expected one of: \"INT\"",
+                "",
+            ],
+        ),
+    ] {
+        let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+        let error = santiago::parser::parse(&grammar, &lexemes).unwrap_err();
+
+        assert_eq!(
+            santiago::diagnostics::Diagnostic::from(&error).render(
+                "input.calc",
+                input,
+                colors
+            ),
+            report.join("\n")
+        );
+    }
+
+    let lexer_rules = comparison::lexer::lexer_rules();
+    let grammar = comparison::grammar::grammar();
+
+    let lexemes = santiago::lexer::lex(&lexer_rules, "1 < 2 < 3").unwrap();
+    let error = santiago::parser::parse(&grammar, &lexemes).unwrap_err();
+    assert_eq!(
+        santiago::diagnostics::Diagnostic::from(&error).render(
+            "input.calc",
+            "1 < 2 < 3",
+            false
+        ),
+        [
+            "error: Error at rule: less",
+            " --> input.calc:1:7",
+            "  |",
+            "1 | 1 < 2 < 3",
+            "  |       ^",
+            "  = note: This operator is non-associative, so it cannot be \
+             chained with: less",
+            "  = note: In: 1 < 2 < 3",
+            "",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn compiled_lexer_rules() {
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "INT" = pattern r"[0-9]+";
+        "DEFAULT" | "IF" = string "if";
+        "DEFAULT" | "ID" = pattern r"[a-z]+";
+        "DEFAULT" | "<" = string "<";
+        "DEFAULT" | "<=" = string "<=";
+        "DEFAULT" | "WS" = pattern r"\s+" => |lexer| lexer.skip();
+    );
+    let compiled = lexer_rules.compile();
+
+    let input = "if iff x<=1<2";
+    let lexemes = santiago::lexer::lex(&compiled, input).unwrap();
+    assert_eq!(
+        lexemes
+            .iter()
+            .map(|lexeme| (lexeme.kind.as_str(), lexeme.raw.as_str()))
+            .collect::<Vec<(&str, &str)>>(),
+        vec![
+            ("IF", "if"),
+            ("ID", "iff"),
+            ("ID", "x"),
+            ("<=", "<="),
+            ("INT", "1"),
+            ("<", "<"),
+            ("INT", "2"),
+        ]
+    );
+    assert_eq!(lexemes, santiago::lexer::lex(&lexer_rules, input).unwrap());
+
+    let error = santiago::lexer::lex(&compiled, "if #").unwrap_err();
+    let expected = santiago::lexer::lex(&lexer_rules, "if #").unwrap_err();
+    assert_eq!(error.to_string(), expected.to_string());
+    assert_eq!(error.byte_index, 3);
+
+    // Patterns keep the match their own regular expression prefers,
+    // which is not always the longest one.
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "COMMENT" = pattern r"/\*(.|\n)*?\*/";
+        "DEFAULT" | "AB" = pattern r"a|ab";
+        "DEFAULT" | "B" = string "b";
+        "DEFAULT" | "*/" = string "*/";
+        "DEFAULT" | "WS" = pattern r"\s+" => |lexer| lexer.skip();
+    );
+    let input = "/* a */ ab */";
+    let lexemes = santiago::lexer::lex(&lexer_rules.compile(), input).unwrap();
+    assert_eq!(
+        lexemes
+            .iter()
+            .map(|lexeme| (lexeme.kind.as_str(), lexeme.raw.as_str()))
+            .collect::<Vec<(&str, &str)>>(),
+        vec![("COMMENT", "/* a */"), ("AB", "a"), ("B", "b"), ("*/", "*/")]
+    );
+    assert_eq!(lexemes, santiago::lexer::lex(&lexer_rules, input).unwrap());
+}
+
+#[test]
+fn lexer_closure_actions() {
+    use santiago::lexer::LexerBuilder;
+    use std::collections::HashSet;
+
+    let reserved: HashSet<&str> = HashSet::from(["if", "else"]);
+    let uppercase = true;
+
+    let lexer_rules = LexerBuilder::new()
+        .pattern(&["DEFAULT"], "ID", r"[a-z]+", move |lexer| {
+            if reserved.contains(lexer.matched()) {
+                lexer.error("Reserved word")
+            } else {
+                lexer.take_and_map(|matched| {
+                    if uppercase {
+                        matched.to_uppercase()
+                    } else {
+                        matched.to_string()
+                    }
+                })
+            }
+        })
+        .pattern(&["DEFAULT"], "WS", r"\s+", |lexer| lexer.skip())
+        .finish();
+
+    assert_eq!(
+        santiago::lexer::lex(&lexer_rules, "foo bar")
+            .unwrap()
+            .iter()
+            .map(|lexeme| lexeme.raw.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["FOO", "BAR"]
+    );
+    assert_eq!(
+        santiago::lexer::lex(&lexer_rules, "foo if").unwrap_err().message,
+        "Reserved word"
+    );
+
+    let suffix = String::from("!");
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "INT" = pattern r"[0-9]+" => move |lexer| {
+            let suffix = suffix.clone();
+
+            lexer.take_and_map(move |matched| format!("{matched}{suffix}"))
+        };
+    );
+
+    assert_eq!(santiago::lexer::lex(&lexer_rules, "42").unwrap()[0].raw, "42!");
+}
+
+#[test]
+fn lexer_context() {
+    use santiago::lexer::LexerBuilder;
+
+    let lexer_rules = LexerBuilder::<Vec<usize>>::with_context()
+        .string(&["DEFAULT"], "(", "(", |lexer| {
+            lexer.context.push(lexer.current_match_len);
+            lexer.take()
+        })
+        .string(&["DEFAULT"], ")", ")", |lexer| match lexer.context.pop() {
+            Some(_) => lexer.take(),
+            None => lexer.error("Unbalanced parenthesis"),
+        })
+        .pattern(&["DEFAULT"], "WS", r"\s+", |lexer| lexer.skip())
+        .finish();
+
+    let (lexemes, context) =
+        santiago::lexer::lex_with_context(&lexer_rules, "(( )", vec![])
+            .unwrap();
+    assert_eq!(lexemes.len(), 3);
+    assert_eq!(context, vec![1]);
+    assert_eq!(
+        santiago::lexer::lex_with_context(&lexer_rules, "())", vec![])
+            .unwrap_err()
+            .message,
+        "Unbalanced parenthesis"
+    );
+
+    let lexer_rules = santiago::lexer_rules!(
+        context std::collections::HashMap<String, usize>;
+        "DEFAULT" | "ID" = pattern r"[a-z]+" => |lexer| {
+            let matched = lexer.matched().to_string();
+            *lexer.context.entry(matched).or_default() += 1;
+            lexer.take()
+        };
+        "DEFAULT" | "WS" = pattern r"\s+" => |lexer| lexer.skip();
+    );
+
+    let (_, context) = santiago::lexer::lex_with_context(
+        &lexer_rules,
+        "a b a",
+        Default::default(),
+    )
+    .unwrap();
+    assert_eq!(context["a"], 2);
+    assert_eq!(context["b"], 1);
+}
+
+#[test]
+fn lexer_multiple_lexemes() {
+    use santiago::lexer::NextLexeme;
+
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | ">" = string ">";
+        "DEFAULT" | ">>" = string ">>" => |lexer| {
+            lexer.take_split(&[(">", 1), (">", 1)])
+        };
+        "DEFAULT" | "ID" = pattern r"[a-z]+";
+        "DEFAULT" | "DEDENTS" = pattern r"\.+" => |lexer| {
+            let dedents = vec![lexer.synthetic("DEDENT"); lexer.matched().len()];
+            lexer.skip();
+
+            NextLexeme::Lexemes(dedents)
+        };
+        "DEFAULT" | "BAD" = string "%%" => |lexer| {
+            lexer.take_split(&[("%", 1)])
+        };
+    );
+
+    let lexemes = santiago::lexer::lex(&lexer_rules, "a>>b..c").unwrap();
+    assert_eq!(
+        lexemes.iter().map(|lexeme| lexeme.to_string()).collect::<Vec<_>>(),
+        vec![
+            r#"ID "a" (1, 1)"#,
+            r#"> ">" (1, 2)"#,
+            r#"> ">" (1, 3)"#,
+            r#"ID "b" (1, 4)"#,
+            r#"DEDENT "" (1, 5)"#,
+            r#"DEDENT "" (1, 5)"#,
+            r#"ID "c" (1, 7)"#,
+        ]
+    );
+    assert_eq!(lexemes[2].byte_range(), 2..3);
+    assert_eq!(lexemes[4].byte_range(), 4..4);
+
+    assert_eq!(
+        santiago::lexer::lex(&lexer_rules, "%%").unwrap_err().message,
+        "Cannot split a match of 2 bytes into parts of [1] bytes"
+    );
+}
+
+#[test]
+fn lexer_iterator() {
+    let lexer_rules = calculator::lexer::lexer_rules();
+
+    let mut lexer = santiago::lexer::Lexer::new(&lexer_rules, "1 + 2 a 3");
+    assert_eq!(lexer.next().unwrap().unwrap().raw, "1");
+    assert_eq!(lexer.next().unwrap().unwrap().raw, "+");
+    assert_eq!(lexer.next().unwrap().unwrap().raw, "2");
+    assert_eq!(lexer.next().unwrap().unwrap_err().byte_index, 6);
+    assert!(lexer.next().is_none());
+
+    let input = "10 + 20\n+ 3";
+    assert_eq!(
+        santiago::lexer::Lexer::new(&lexer_rules, input)
+            .map(|lexeme| std::rc::Rc::new(lexeme.unwrap()))
+            .collect::<Vec<std::rc::Rc<santiago::lexer::Lexeme>>>(),
+        santiago::lexer::lex(&lexer_rules, input).unwrap()
+    );
+
+    // Lexing stops as soon as we stop asking for Lexemes.
+    let input = "1 + ".repeat(1_000_000);
+    assert_eq!(
+        santiago::lexer::Lexer::new(&lexer_rules, &input)
+            .take(3)
+            .map(|lexeme| lexeme.unwrap().raw)
+            .collect::<Vec<String>>(),
+        vec!["1", "+", "1"]
+    );
+}
+
+#[test]
+fn lex_with_recovery() {
+    let input = "1 + ab $ 2 &é 3";
+    let (lexemes, errors) = santiago::lexer::lex_with_recovery(
+        &calculator::lexer::lexer_rules(),
+        input,
+    );
+
+    assert_eq!(
+        lexemes
+            .iter()
+            .map(|lexeme| (lexeme.kind.as_str(), &input[lexeme.byte_range()]))
+            .collect::<Vec<(&str, &str)>>(),
+        vec![
+            ("INT", "1"),
+            ("+", "+"),
+            ("ERROR", "ab"),
+            ("ERROR", "$"),
+            ("INT", "2"),
+            ("ERROR", "&é"),
+            ("INT", "3"),
+        ]
+    );
+    assert_eq!(
+        errors
+            .iter()
+            .map(|error| (error.byte_index, error.match_len))
+            .collect::<Vec<(usize, Option<usize>)>>(),
+        vec![(4, None), (7, None), (11, None)]
+    );
+    assert_eq!(
+        santiago::lexer::lex(&calculator::lexer::lexer_rules(), input)
+            .unwrap_err()
+            .byte_index,
+        4
+    );
+
+    // Errors reported by the actions of the rules.
+    let input = "a = ` + b";
+    let (lexemes, errors) =
+        santiago::lexer::lex_with_recovery(&nix::lexer::lexer_rules(), input);
+
+    assert_eq!(
+        lexemes
+            .iter()
+            .map(|lexeme| (lexeme.kind.as_str(), &input[lexeme.byte_range()]))
+            .collect::<Vec<(&str, &str)>>(),
+        vec![("ID", "a"), ("=", "="), ("ERROR", "`"), ("+", "+"), ("ID", "b")]
+    );
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Unexpected input");
+    assert_eq!(errors[0].match_len, Some(1));
+}
+
+#[test]
+fn lex_and_parse() {
+    use santiago::parser::LexAndParseError;
+
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "LET" = string "let";
+        "DEFAULT" | "ID" = pattern r"[a-z]+";
+        "DEFAULT" | "=" = string "=";
+        "DEFAULT" | ";" = string ";";
+        "DEFAULT" | "/" = string "/";
+        "DEFAULT" | "REGEX" = pattern r"/[^/]*/";
+        "DEFAULT" | "WS" = pattern r"\s" => |lexer| lexer.skip();
+    );
+    let grammar = santiago::grammar!(
+        "statements" => rules "statement";
+        "statements" => rules "statements" "statement";
+
+        "statement" => rules "LET" "ID" "=" "expr" ";";
+
+        "expr" => rules "ID";
+        "expr" => rules "REGEX";
+        "expr" => rules "expr" "/" "expr";
+
+        "LET" => lexemes "LET";
+        "ID" => lexemes "ID";
+        "REGEX" => lexemes "REGEX";
+        "=" => lexemes "=";
+        ";" => lexemes ";";
+        "/" => lexemes "/";
+
+        santiago::grammar::Associativity::Left => rules "/";
+    );
+
+    let kinds = |lexemes: &[std::rc::Rc<santiago::lexer::Lexeme>]| {
+        lexemes.iter().map(|lexeme| lexeme.kind.clone()).collect::<Vec<_>>()
+    };
+
+    // The normal lexer cannot tell these apart.
+    let input = "let let = let / b / c; let re = / b /;";
+    let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+    assert_eq!(kinds(&lexemes)[..6], ["LET", "LET", "=", "LET", "REGEX", "ID"]);
+    assert!(santiago::parser::parse(&grammar, &lexemes).is_err());
+
+    let (lexemes, trees) =
+        santiago::parser::lex_and_parse(&lexer_rules, &grammar, input).unwrap();
+    assert_eq!(
+        kinds(&lexemes),
+        [
+            "LET", "ID", "=", "ID", "/", "ID", "/", "ID", ";", "LET", "ID",
+            "=", "REGEX", ";"
+        ]
+    );
+    assert_eq!(trees.len(), 1);
+    assert_eq!(trees[0].byte_range(), 0..input.len());
+
+    match santiago::parser::lex_and_parse(&lexer_rules, &grammar, "let a b;") {
+        Err(LexAndParseError::Parser(error)) => {
+            assert_eq!(error.found.unwrap().raw, "b");
+            assert_eq!(error.expected_lexemes, ["="]);
+        }
+        result => panic!("{result:?}"),
+    }
+    match santiago::parser::lex_and_parse(&lexer_rules, &grammar, "let a = ") {
+        Err(LexAndParseError::Parser(error)) => {
+            assert!(error.found.is_none());
+        }
+        result => panic!("{result:?}"),
+    }
+    match santiago::parser::lex_and_parse(&lexer_rules, &grammar, "let a = 1;")
+    {
+        Err(LexAndParseError::Lexer(error)) => {
+            assert_eq!(error.byte_index, 8);
+        }
+        result => panic!("{result:?}"),
+    }
+}
+
+#[test]
+fn lexeme_lattice() {
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "ID" = pattern r"[a-z]+";
+        "DEFAULT" | "<" = string "<";
+        "DEFAULT" | ">" = string ">";
+        "DEFAULT" | ">>" = string ">>";
+        "DEFAULT" | "," = string ",";
+        "DEFAULT" | "WS" = pattern r"\s" => |lexer| lexer.skip();
+    );
+    let grammar = santiago::grammar!(
+        "statement" => rules "type";
+        "statement" => rules "ID" ">>" "ID";
+
+        "type" => rules "ID";
+        "type" => rules "ID" "<" "types" ">";
+
+        "types" => rules "type";
+        "types" => rules "types" "," "type";
+
+        "ID" => lexemes "ID";
+        "<" => lexemes "<";
+        ">" => lexemes ">";
+        ">>" => lexemes ">>";
+        "," => lexemes ",";
+    );
+
+    let raws = |lexemes: &[std::rc::Rc<santiago::lexer::Lexeme>]| {
+        lexemes.iter().map(|lexeme| lexeme.raw.clone()).collect::<Vec<_>>()
+    };
+
+    // The normal lexer commits to ">>".
+    let input = "map<a, vec<b>>";
+    let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+    assert!(santiago::parser::parse(&grammar, &lexemes).is_err());
+
+    let lattice = santiago::lexer::lex_lattice(&lexer_rules, input).unwrap();
+    assert_eq!(lattice.tokenisations().len(), 2);
+    assert_eq!(
+        lattice.to_string(),
+        [
+            "0 -> 1 ID \"map\" (1, 1)",
+            "1 -> 2 < \"<\" (1, 4)",
+            "2 -> 3 ID \"a\" (1, 5)",
+            "3 -> 4 , \",\" (1, 6)",
+            "4 -> 5 ID \"vec\" (1, 8)",
+            "5 -> 6 < \"<\" (1, 11)",
+            "6 -> 7 ID \"b\" (1, 12)",
+            "7 -> 8 > \">\" (1, 13)",
+            "7 -> 9 >> \">>\" (1, 13)",
+            "8 -> 9 > \">\" (1, 14)",
+            "Finals: {9}",
+        ]
+        .join("\n")
+    );
+
+    let results = santiago::parser::parse_lattice(&grammar, &lattice).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        raws(&results[0].0),
+        ["map", "<", "a", ",", "vec", "<", "b", ">", ">"]
+    );
+    assert_eq!(results[0].1.len(), 1);
+
+    let lattice = santiago::lexer::lex_lattice(&lexer_rules, "a >> b").unwrap();
+    let results = santiago::parser::parse_lattice(&grammar, &lattice).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(raws(&results[0].0), ["a", ">>", "b"]);
+
+    let lattice = santiago::lexer::lex_lattice(&lexer_rules, "a<b>>").unwrap();
+    let error =
+        santiago::parser::parse_lattice(&grammar, &lattice).unwrap_err();
+    assert_eq!(error.found.unwrap().raw, ">");
+    assert_eq!(error.at.unwrap().raw, ">");
+
+    let error = santiago::lexer::lex_lattice(&lexer_rules, "a<b$").unwrap_err();
+    assert_eq!(error.byte_index, 3);
+
+    // The closing brackets can be split in exponentially many ways.
+    let depth = 64;
+    let input = format!("{}a{}", "a<".repeat(depth), ">".repeat(depth));
+    let lattice = santiago::lexer::lex_lattice(&lexer_rules, &input).unwrap();
+    let results = santiago::parser::parse_lattice(&grammar, &lattice).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0.len(), 3 * depth + 1);
+    assert_eq!(results[0].1.len(), 1);
+    assert_eq!(results[0].1[0].lexeme_range(), Some(0..3 * depth + 1));
+    assert_eq!(results[0].1[0].byte_range(), 0..input.len());
+}
+
+#[test]
+fn scannerless() {
+    let grammar = santiago::grammar!(
+        "statements" => rules "statement";
+        "statements" => rules "statements" "statement"
+            => |values| format!("{} {}", values[0], values[1]);
+
+        "statement" => rules "if";
+        "statement" => rules "id";
+        "statement" => rules "number";
+        "statement" => rules "_";
+
+        "if" => string "if" => |_| "if".to_string();
+        "if" => not_followed_by "[a-z0-9_]";
+
+        "id" => rules "id_chars" => |values| format!("id({})", values[0]);
+        "id" => not_followed_by "[a-z0-9_]";
+        "id" => reject "if" "else";
+        "id_chars" => rules "letter";
+        "id_chars" => rules "id_chars" "id_char" => |values| values.concat();
+        "letter" => chars "[a-z_]" => |lexemes| lexemes[0].raw.clone();
+        "id_char" => chars "[a-z0-9_]" => |lexemes| lexemes[0].raw.clone();
+
+        "number" => rules "digits" => |values| format!("number({})", values[0]);
+        "number" => not_followed_by "[0-9]";
+        "digits" => rules "digit";
+        "digits" => rules "digits" "digit" => |values| values.concat();
+        "digit" => chars "[0-9]" => |lexemes| lexemes[0].raw.clone();
+
+        "_" => chars "[ \\t\\n]" => |_| "_".to_string();
+    );
+
+    let trees =
+        santiago::parser::parse_str(&grammar, "if iffy\t42 x1").unwrap();
+    assert_eq!(trees.len(), 1);
+    assert_eq!(
+        trees[0].as_abstract_syntax_tree(),
+        "if _ id(iffy) _ number(42) _ id(x1)"
+    );
+
+    // Without restrictions, "ifx" would also be "if" followed by "x".
+    let trees = santiago::parser::parse_str(&grammar, "ifx").unwrap();
+    assert_eq!(trees.len(), 1);
+    assert_eq!(trees[0].as_abstract_syntax_tree(), "id(ifx)");
+
+    let lexemes = santiago::lexer::lex_chars("1 +");
+    assert_eq!(lexemes[2].position.column, 3);
+    let error = santiago::parser::parse(&grammar, &lexemes).unwrap_err();
+    assert_eq!(error.found.unwrap().raw, "+");
+
+    let error = santiago::grammar::GrammarBuilder::<()>::new()
+        .rule_to_chars("a", "[z-a]", |_| ())
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        error.to_string(),
+        "Error at rule: a\nThe character class: [z-a]\nIs not valid: The range \
+         'z'-'a' is out of order"
+    );
+    let error = santiago::grammar::GrammarBuilder::<()>::new()
+        .rule_to_string("a", "a", |_| ())
+        .not_followed_by("b", "[a]")
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        error.to_string(),
+        "Error while trying to restrict a rule with name: b\nWhich has not \
+         been previously defined."
+    );
+}
+
+#[test]
+fn trivia() {
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "INT" = pattern r"[0-9]+";
+        "DEFAULT" | "+" = string "+";
+        "DEFAULT" | "COMMENT" = pattern r"#[^\n]*" => |lexer| lexer.skip();
+        "DEFAULT" | "WS" = pattern r"\s+" => |lexer| lexer.skip();
+    );
+    let grammar = santiago::grammar!(
+        "sum" => rules "sum" "+" "int";
+        "sum" => rules "int";
+
+        "int" => lexemes "INT";
+        "+" => lexemes "+";
+    );
+
+    let trivia = |trivia: &[santiago::lexer::Trivia]| {
+        trivia
+            .iter()
+            .map(|trivia| (trivia.kind.clone(), trivia.raw.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let input = "  1 # one\n+ 2  # two\n";
+    let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+    assert_eq!(
+        trivia(&lexemes[0].leading_trivia),
+        [("WS".into(), "  ".into())]
+    );
+    assert_eq!(
+        trivia(&lexemes[1].leading_trivia),
+        [
+            ("WS".into(), " ".into()),
+            ("COMMENT".into(), "# one".into()),
+            ("WS".into(), "\n".into())
+        ]
+    );
+    assert_eq!(lexemes[1].leading_trivia[1].byte_range(), 4..9);
+    assert!(lexemes[1].trailing_trivia.is_empty());
+    assert_eq!(
+        trivia(&lexemes[2].trailing_trivia),
+        [
+            ("WS".into(), "  ".into()),
+            ("COMMENT".into(), "# two".into()),
+            ("WS".into(), "\n".into())
+        ]
+    );
+
+    let trees = santiago::parser::parse(&grammar, &lexemes).unwrap();
+    let cst = trees[0].cst();
+    assert_eq!(cst.to_string(), input);
+    assert_eq!(cst.lexemes().len(), 3);
+
+    let (_, trees) =
+        santiago::parser::lex_and_parse(&lexer_rules, &grammar, input).unwrap();
+    assert_eq!(trees[0].cst().to_string(), input);
+
+    // Retried lexemes keep what they matched, but cover no input.
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "MARK" = string "x" => |lexer| {
+            lexer.push_state("X");
+            lexer.take_and_retry()
+        };
+        "X" | "X" = string "x" => |lexer| {
+            lexer.pop_state();
+            lexer.take()
+        };
+        "DEFAULT" | "WS" = pattern r"\s+" => |lexer| lexer.skip();
+    );
+    let grammar = santiago::grammar!(
+        "marks" => lexemes "MARK" "X" "MARK" "X";
+    );
+    let input = " x  x ";
+    let lexemes = santiago::lexer::lex(&lexer_rules, input).unwrap();
+    assert_eq!(lexemes[0].raw, "x");
+    assert_eq!(lexemes[0].byte_range(), 1..1);
+    let trees = santiago::parser::parse(&grammar, &lexemes).unwrap();
+    let cst = trees[0].cst();
+    assert_eq!(cst.lexemes().len(), 4);
+    assert_eq!(cst.to_string(), input);
+
+    // Newlines and indentation are either trivia or lexemes, but not both.
+    for case in ["brackets", "functions"] {
+        let input = std::fs::read_to_string(format!(
+            "tests/indentation/cases/{case}/input"
+        ))
+        .unwrap();
+        let lexemes =
+            santiago::lexer::lex(&indentation::lexer::lexer_rules(), &input)
+                .unwrap();
+        let trees =
+            santiago::parser::parse(&indentation::grammar::grammar(), &lexemes)
+                .unwrap();
+        assert_eq!(trees[0].cst().to_string(), input);
+    }
+}
+
+#[test]
+fn document() {
+    use santiago::parser::Document;
+    use std::rc::Rc;
+
+    // The Document is always the same as lexing and parsing from scratch.
+    fn check(
+        lexer_rules: &santiago::lexer::LexerRules,
+        grammar: &santiago::grammar::Grammar<()>,
+        document: &Document<()>,
+    ) {
+        let input = document.input();
+
+        let lexemes = match santiago::lexer::lex(lexer_rules, input) {
+            Ok(lexemes) => lexemes,
+            Err(_) => santiago::lexer::lex_with_recovery(lexer_rules, input)
+                .0
+                .into_iter()
+                .take_while(|lexeme| lexeme.kind != "ERROR")
+                .collect(),
+        };
+        assert_eq!(document.lexemes(), lexemes);
+
+        let expected =
+            santiago::parser::lex_and_parse(lexer_rules, grammar, input)
+                .map(|(_, trees)| trees);
+        match (document.parse(), expected) {
+            (Ok(trees), Ok(expected)) => {
+                assert_eq!(format!("{trees:?}"), format!("{expected:?}"));
+                for tree in &trees {
+                    assert_eq!(tree.cst().to_string(), input);
+                }
+            }
+            (Err(error), Err(expected)) => {
+                assert_eq!(error.to_string(), expected.to_string());
+            }
+            (result, expected) => panic!("{result:?}\n{expected:?}"),
+        }
+    }
+
+    let lexer_rules = indentation::lexer::lexer_rules();
+    let grammar = indentation::grammar::grammar();
+    let input =
+        std::fs::read_to_string("tests/indentation/cases/functions/input")
+            .unwrap();
+
+    let mut document = Document::new(&lexer_rules, &grammar, &input);
+    check(&lexer_rules, &grammar, &document);
+
+    // The Lexemes before an edit are reused,
+    // and so are the ones after it if they did not move.
+    let before = document.lexemes().to_vec();
+    let index = document.input().find("sign(2)").unwrap() + 5;
+    document.edit(index..index + 1, "3");
+    check(&lexer_rules, &grammar, &document);
+    let after = document.lexemes();
+    assert_eq!(after.len(), before.len());
+    assert!(Rc::ptr_eq(&after[0], &before[0]));
+    assert!(Rc::ptr_eq(after.last().unwrap(), before.last().unwrap()));
+    assert!(!Rc::ptr_eq(&after[after.len() - 5], &before[before.len() - 5]));
+
+    let find = |document: &Document<()>, text: &str| {
+        let index = document.input().find(text).unwrap();
+        index..index + text.len()
+    };
+    let edits: &[(&str, &str)] = &[
+        ("total = a + b", "sum = a + b + 1"),
+        ("    return total", "    x = [\n1]\n    return total"),
+        ("def sign", "\n\ndef signs"),
+        // An error in the Lexer, and its fix.
+        ("        return 1", "   return 1"),
+        ("   return 1", "        return 1"),
+        // An error in the parser, and its fix.
+        ("print(", "print(("),
+        ("print((", "print("),
+        ("def add", "def  add"),
+        ("(a, b):", "(a, b):  # Trailing comment"),
+        ("sign(3)))", "sign(3)))\n# The end\n"),
+    ];
+    for (text, replacement) in edits {
+        let range = find(&document, text);
+        document.edit(range, replacement);
+        check(&lexer_rules, &grammar, &document);
+    }
+
+    let len = document.input().len();
+    document.edit(0..len, "");
+    check(&lexer_rules, &grammar, &document);
+    document.edit(0..0, &input);
+    check(&lexer_rules, &grammar, &document);
+
+    // The Parse Trees before an edit are reused.
+    let before = document.parse().unwrap();
+    let range = find(&document, "sign(2)))");
+    document.edit(range, "sign(4)))");
+    check(&lexer_rules, &grammar, &document);
+    let after = document.parse().unwrap();
+    let first_leaf = |tree: &Rc<santiago::parser::Tree<()>>| match &**tree {
+        santiago::parser::Tree::Node { leaves, .. } => leaves[0].clone(),
+        tree => panic!("{tree}"),
+    };
+    // The statements before the last one.
+    assert!(Rc::ptr_eq(
+        &first_leaf(&first_leaf(&before[0])),
+        &first_leaf(&first_leaf(&after[0]))
+    ));
+    assert!(!Rc::ptr_eq(&before[0], &after[0]));
+
+    // The longest match of a rule may look past the Lexemes after it.
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "A" = pattern r"a(bcd)?";
+        "DEFAULT" | "B" = string "b";
+        "DEFAULT" | "C" = string "c";
+        "DEFAULT" | "D" = string "d";
+        "DEFAULT" | "E" = string "e";
+    );
+    let grammar = santiago::grammar!(
+        "letters" => rules "letter";
+        "letters" => rules "letters" "letter";
+
+        "letter" => lexemes "A";
+        "letter" => lexemes "B";
+        "letter" => lexemes "C";
+        "letter" => lexemes "D";
+        "letter" => lexemes "E";
+    );
+    let mut document = Document::new(&lexer_rules, &grammar, "abce");
+    document.edit(3..4, "d");
+    assert_eq!(
+        document.lexemes(),
+        santiago::lexer::lex(&lexer_rules, "abcd").unwrap()
+    );
+    assert_eq!(document.lexemes().len(), 1);
+    assert_eq!(document.parse().unwrap().len(), 1);
+
+    let lexer_rules = calculator::lexer::lexer_rules();
+    let grammar = calculator::grammar::grammar();
+
+    // The old columns before the edit are not reused.
+    let mut document = Document::new(&lexer_rules, &grammar, "/-*233-");
+    document.edit(6..6, "-*");
+    check(&lexer_rules, &grammar, &document);
+
+    // The Trivia at the end of the input is kept when the Lexer resyncs.
+    let mut document = Document::new(&lexer_rules, &grammar, "1 + 23 ");
+    document.edit(4..6, "4");
+    check(&lexer_rules, &grammar, &document);
+    let trees = document.parse().unwrap();
+    assert_eq!(trees[0].cst().to_string(), "1 + 4 ");
+
+    // Random edits, from a linear congruential generator.
+    let mut seed: u64 = 42;
+    let mut random = |max: usize| {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as usize % max
+    };
+    let alphabet: Vec<char> = "0123456789+-*/ \n".chars().collect();
+    let mut document = Document::new(&lexer_rules, &grammar, "1 + 2 * 3");
+    for _ in 0..500 {
+        let len = document.input().len();
+        let start = random(len + 1);
+        let end = start + random(len - start + 1).min(3);
+        let replacement: String =
+            (0..random(4)).map(|_| alphabet[random(alphabet.len())]).collect();
+        document.edit(start..end, &replacement);
+        check(&lexer_rules, &grammar, &document);
+    }
+}
+
+#[test]
+fn lexeme_spans() {
+    let input = "10 + 20\n+ 3";
+    let lexemes =
+        santiago::lexer::lex(&integer_addition::lexer::lexer_rules(), input)
+            .unwrap();
+
+    let spans: Vec<(&str, usize, usize, usize, usize)> = lexemes
+        .iter()
+        .map(|lexeme| {
+            assert_eq!(&input[lexeme.byte_range()], lexeme.raw);
+            (
+                &input[lexeme.byte_range()],
+                lexeme.position.line,
+                lexeme.position.column,
+                lexeme.position_end.line,
+                lexeme.position_end.column,
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        spans,
+        vec![
+            ("10", 1, 1, 1, 3),
+            ("+", 1, 4, 1, 5),
+            ("20", 1, 6, 1, 8),
+            ("+", 2, 1, 2, 2),
+            ("3", 2, 3, 2, 4),
+        ]
+    );
+    assert_eq!(format!("{:?}", lexemes[3].position), "(2, 1, byte 8)");
+    assert_eq!(format!("{}", lexemes[3].position), "(2, 1)");
+}
+
+#[test]
+fn tree_spans() {
+    fn spans<AST>(
+        input: &str,
+        parse_tree: &santiago::parser::Tree<AST>,
+    ) -> Vec<(String, std::ops::Range<usize>, String)> {
+        parse_tree
+            .traverse_in_pre_order()
+            .into_iter()
+            .filter_map(|tree| match tree {
+                santiago::parser::Tree::Node { rule_name, .. } => Some((
+                    rule_name.to_string(),
+                    tree.lexeme_range().unwrap(),
+                    input[tree.byte_range()].to_string(),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    let input = "1 + 2 *  3";
+    let lexemes =
+        santiago::lexer::lex(&calculator::lexer::lexer_rules(), input).unwrap();
+    let parse_trees =
+        santiago::parser::parse(&calculator::grammar::grammar(), &lexemes)
+            .unwrap();
+
+    assert_eq!(
+        spans(input, &parse_trees[0]),
+        vec![
+            ("Γ".to_string(), 0..5, "1 + 2 *  3".to_string()),
+            ("expr".to_string(), 0..5, "1 + 2 *  3".to_string()),
+            ("expr".to_string(), 0..1, "1".to_string()),
+            ("int".to_string(), 0..1, "1".to_string()),
+            ("add".to_string(), 1..2, "+".to_string()),
+            ("expr".to_string(), 2..5, "2 *  3".to_string()),
+            ("expr".to_string(), 2..3, "2".to_string()),
+            ("int".to_string(), 2..3, "2".to_string()),
+            ("multiply".to_string(), 3..4, "*".to_string()),
+            ("expr".to_string(), 4..5, "3".to_string()),
+            ("int".to_string(), 4..5, "3".to_string()),
+        ]
+    );
+
+    let input = "ab";
+    let lexemes =
+        santiago::lexer::lex(&smallest::lexer::lexer_rules(), input).unwrap();
+    let parse_trees =
+        santiago::parser::parse(&smallest::grammar::grammar(), &lexemes)
+            .unwrap();
+
+    assert_eq!(
+        spans(input, &parse_trees[0]),
+        vec![
+            ("Γ".to_string(), 0..2, "ab".to_string()),
+            ("chars".to_string(), 0..2, "ab".to_string()),
+            ("chars".to_string(), 0..1, "a".to_string()),
+            ("chars".to_string(), 0..0, "".to_string()),
+            ("char".to_string(), 0..1, "a".to_string()),
+            ("char".to_string(), 1..2, "b".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn grammar_errors() {
+    use santiago::grammar::Associativity;
+    use santiago::grammar::GrammarBuilder;
+    use santiago::grammar::GrammarError;
+
+    let errors = GrammarBuilder::<()>::new().try_finish().err().unwrap();
+    assert!(matches!(errors[..], [GrammarError::Empty]));
+    // The lenient finish() accepts it, as in previous versions.
+    assert!(GrammarBuilder::<()>::new().finish().rules.is_empty());
+
+    // All the errors are reported at once.
+    let errors = GrammarBuilder::new()
+        .rule_to_rules("sum", &["int", "plus", "int"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .disambiguate(Associativity::Left, &["minus"])
+        .try_finish()
+        .err()
+        .unwrap();
+    assert_eq!(
+        errors.iter().map(GrammarError::to_string).collect::<Vec<_>>(),
+        [
+            "Error while trying to disambiguate a rule with name: \
+             minus\nWhich has not been previously defined.",
+            "Error at rule: int\nIn production: lexemes \"INT\"\nThis \
+             production has been defined more than once.",
+            "Error at rule: sum\nIn production: rules \"int\" \"plus\" \
+             \"int\"\nYour grammar references a rule with name: plus\nBut \
+             this rule has not been defined in the grammar.",
+        ]
+    );
+
+    // Duplicated productions are only reported by try_finish().
+    let grammar = GrammarBuilder::new()
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .finish();
+    let lexemes = santiago::lexer::lex(
+        &santiago::lexer_rules!("DEFAULT" | "INT" = pattern "[0-9]+";),
+        "1",
+    )
+    .unwrap();
+    assert_eq!(santiago::parser::parse(&grammar, &lexemes).unwrap().len(), 1);
+
+    let error = GrammarBuilder::new()
+        .rule_to_rules("sum", &["int", "plus", "int"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    match error {
+        GrammarError::UndefinedRule { rule_name, production, symbol } => {
+            assert_eq!(*rule_name, "sum");
+            assert_eq!(production.symbols, ["int", "plus", "int"]);
+            assert_eq!(symbol, "plus");
+        }
+        _ => panic!("{error}"),
+    }
+
+    let error = GrammarBuilder::new()
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .disambiguate(Associativity::Left, &["plus"])
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    match error {
+        GrammarError::UndefinedDisambiguation { rule_name } => {
+            assert_eq!(rule_name, "plus");
+        }
+        _ => panic!("{error}"),
+    }
+
+    let error = GrammarBuilder::new()
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    match error {
+        GrammarError::DuplicateProduction { rule_name, production } => {
+            assert_eq!(*rule_name, "int");
+            assert_eq!(production.symbols, ["INT"]);
+        }
+        _ => panic!("{error}"),
+    }
+
+    let error = GrammarBuilder::<()>::new()
+        .precedence("UMINUS")
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    match error {
+        GrammarError::PrecedenceWithoutProduction { precedence } => {
+            assert_eq!(precedence, "UMINUS");
+        }
+        _ => panic!("{error}"),
+    }
+
+    let error = GrammarBuilder::<()>::new()
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .precedence("UMINUS")
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    match error {
+        GrammarError::PrecedenceWithoutProduction { precedence } => {
+            assert_eq!(precedence, "UMINUS");
+        }
+        _ => panic!("{error}"),
+    }
+
+    // Precedences can be disambiguated before they are used.
+    assert!(GrammarBuilder::<()>::new()
+        .disambiguate(Associativity::None, &["UMINUS"])
+        .rule_to_rules("expr", &["minus", "int"], |_| ())
+        .precedence("UMINUS")
+        .rule_to_lexemes("minus", &["-"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .try_finish()
+        .is_ok());
+
+    let error = GrammarBuilder::new()
+        .rule_to_rules("expr", &["minus", "int"], |_| ())
+        .precedence("UMINUS")
+        .rule_to_lexemes("minus", &["-"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .try_finish()
+        .err()
+        .unwrap()
+        .remove(0);
+    match error {
+        GrammarError::UndefinedPrecedence {
+            rule_name,
+            production,
+            precedence,
+        } => {
+            assert_eq!(*rule_name, "expr");
+            assert_eq!(production.symbols, ["minus", "int"]);
+            assert_eq!(precedence, "UMINUS");
+        }
+        _ => panic!("{error}"),
+    }
+
+    let grammar = santiago::grammar::Grammar::<()> {
+        rules: std::collections::HashMap::new(),
+    };
+    assert!(santiago::parser::parse(&grammar, &[]).is_err());
+}
+
+#[test]
+fn grammar_analysis() {
+    use santiago::grammar::GrammarBuilder;
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    fn sorted<T: ToString>(set: &HashSet<T>) -> Vec<String> {
+        let mut set: Vec<String> = set.iter().map(T::to_string).collect();
+        set.sort();
+        set
+    }
+
+    let grammar = GrammarBuilder::new()
+        .rule_to_rules("expr", &["expr", "plus", "expr"], |_| ())
+        .rule_to_rules("expr", &["optional", "int", "optional"], |_| ())
+        .rule_to_rules("expr", &["optional", "expr"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .rule_to_lexemes("plus", &["PLUS"], |_| ())
+        .rule_to_lexemes("optional", &[], |_| ())
+        .rule_to_lexemes("optional", &["SEMI"], |_| ())
+        .rule_to_rules("never", &["never", "int"], |_| ())
+        .finish();
+    let analysis = grammar.analyze();
+
+    assert_eq!(sorted(&analysis.unreachable_rules), ["never"]);
+    assert_eq!(sorted(&analysis.unproductive_rules), ["never"]);
+    assert_eq!(sorted(&analysis.nullable_rules), ["optional"]);
+    assert_eq!(sorted(&analysis.cyclic_rules), ["expr"]);
+    assert_eq!(
+        sorted(&analysis.first[&Rc::new("expr".to_string())]),
+        ["INT", "SEMI"]
+    );
+    assert_eq!(
+        sorted(&analysis.follow[&Rc::new("expr".to_string())]),
+        ["PLUS"]
+    );
+    assert_eq!(
+        sorted(&analysis.follow[&Rc::new("int".to_string())]),
+        ["INT", "PLUS", "SEMI"]
+    );
+    assert_eq!(
+        sorted(&analysis.follow[&Rc::new("optional".to_string())]),
+        ["INT", "PLUS", "SEMI"]
+    );
+    assert_eq!(
+        sorted(&analysis.followed_by_end),
+        ["expr", "int", "optional", "Γ"]
+    );
+    assert_eq!(
+        sorted(
+            &analysis
+                .first_of_rules(&["optional".to_string(), "int".to_string()])
+        ),
+        ["INT", "SEMI"]
+    );
+
+    let analysis = nix::grammar::grammar().analyze();
+
+    assert_eq!(
+        sorted(&analysis.unreachable_rules),
+        ["!", "ANY", "COMMENT", "ERROR", "SKIP", "WS"]
+    );
+    assert!(analysis.unproductive_rules.is_empty());
+    assert!(analysis.cyclic_rules.is_empty());
+}
+
+#[test]
+fn grammar_lexeme_kinds_check() {
+    use santiago::grammar::GrammarBuilder;
+
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "INT" = pattern r"[0-9]+";
+        "DEFAULT" | "PLUS" = string "+";
+        "DEFAULT" | "MINUS" = string "-";
+        "DEFAULT" | "WS" = pattern r"\s" => |lexer| lexer.skip();
+    );
+    let grammar = GrammarBuilder::new()
+        .rule_to_rules("sum", &["int", "plus", "int"], |_| ())
+        .rule_to_lexemes("int", &["INT"], |_| ())
+        .rule_to_lexemes("plus", &["PLUSS"], |_| ())
+        .finish();
+    let check = grammar.check_lexer_rules(&lexer_rules);
+
+    assert!(check.has_missing_kinds());
+    assert_eq!(check.missing_kinds.len(), 1);
+    assert_eq!(
+        check.missing_kinds["PLUSS"]
+            .iter()
+            .map(|rule_name| rule_name.as_str())
+            .collect::<Vec<&str>>(),
+        ["plus"]
+    );
+    let mut unused_kinds: Vec<&String> = check.unused_kinds.iter().collect();
+    unused_kinds.sort();
+    assert_eq!(unused_kinds, ["MINUS", "PLUS", "WS"]);
+
+    let check =
+        nix::grammar::grammar().check_lexer_rules(&nix::lexer::lexer_rules());
+
+    assert!(!check.has_missing_kinds());
+    assert!(check.unused_kinds.is_empty());
+}
+
+#[test]
+fn lexer_builder_errors() {
+    use santiago::lexer::LexerBuilder;
+    use santiago::lexer::LexerBuilderError;
+
+    let errors = santiago::try_lexer_rules!(
+        "DEFAULT" "STRING" | "INT" = pattern r"[0-9+";
+        "DEFAULT" | "ID" = pattern r"[a-z]+";
+        "DEFAULT" | "IF" = string "if";
+        "DEFAULT" | "PLUS" = string "+";
+        "DEFAULT" | "ADD" = string "+";
+        "DEFAULT" | "WORD" = pattern r"[a-z]+";
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(errors.len(), 4);
+    assert!(matches!(
+        &errors[0],
+        LexerBuilderError::InvalidPattern { rule_name, states, pattern, .. }
+        if rule_name == "INT"
+            && states == &["DEFAULT", "STRING"]
+            && pattern == "[0-9+"
+    ));
+    assert_eq!(
+        errors[1..],
+        [
+            LexerBuilderError::ShadowedRule {
+                rule_name:   "IF".to_string(),
+                state:       "DEFAULT".to_string(),
+                shadowed_by: "ID".to_string(),
+            },
+            LexerBuilderError::ShadowedRule {
+                rule_name:   "ADD".to_string(),
+                state:       "DEFAULT".to_string(),
+                shadowed_by: "PLUS".to_string(),
+            },
+            LexerBuilderError::ShadowedRule {
+                rule_name:   "WORD".to_string(),
+                state:       "DEFAULT".to_string(),
+                shadowed_by: "ID".to_string(),
+            },
+        ]
+    );
+
+    let errors = LexerBuilder::new()
+        .declare_states(&["STRING"])
+        .string(&["DEFAULT"], "\"", "\"", |lexer| {
+            lexer.push_state("STRING");
+            lexer.take()
+        })
+        .string(&["STRING"], "\"", "\"", |lexer| {
+            lexer.pop_state();
+            lexer.take()
+        })
+        .string(&["COMMENT"], "*/", "*/", |lexer| lexer.skip())
+        .try_finish()
+        .err()
+        .unwrap();
+
+    assert_eq!(
+        errors,
+        [LexerBuilderError::UnreachableState {
+            rule_name: "*/".to_string(),
+            state:     "COMMENT".to_string(),
+        }]
+    );
+
+    assert!(santiago::try_lexer_rules!(
+        "DEFAULT" | "IF" = string "if";
+        "DEFAULT" | "ID" = pattern r"[a-z]+";
+    )
+    .is_ok());
+
+    // The check for undeclared states also applies
+    // when no state has been declared.
+    let errors = santiago::try_lexer_rules!(
+        "DEFAULT" | "QUOTE" = string "\"" => |lexer| {
+            lexer.push_state("STRING");
+            lexer.take()
+        };
+        "STRING" | "QUOTE" = string "\"" => |lexer| {
+            lexer.pop_state();
+            lexer.take()
+        };
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        errors,
+        [LexerBuilderError::UnreachableState {
+            rule_name: "QUOTE".to_string(),
+            state:     "STRING".to_string(),
+        }]
+    );
+    assert!(santiago::try_lexer_rules!(
+        states "STRING";
+        "DEFAULT" | "QUOTE" = string "\"" => |lexer| {
+            lexer.push_state("STRING");
+            lexer.take()
+        };
+        "STRING" | "QUOTE" = string "\"" => |lexer| {
+            lexer.pop_state();
+            lexer.take()
+        };
+    )
+    .is_ok());
+
+    // Whether a pattern with assertions matches depends on the input around
+    // the match, so it does not shadow the rules declared after it.
+    let lexer_rules = santiago::try_lexer_rules!(
+        "DEFAULT" | "WORD" = pattern r"[a-z]+\b";
+        "DEFAULT" | "IF" = string "if";
+        "DEFAULT" | "INT" = pattern r"[0-9]+";
+    )
+    .ok()
+    .unwrap();
+    let lexemes = santiago::lexer::lex(&lexer_rules, "if1").unwrap();
+    assert_eq!(lexemes[0].kind, "IF");
+    assert_eq!(lexemes[1].kind, "INT");
+
+    // Only invalid patterns make the infallible variants panic.
+    let lexer_rules = santiago::lexer_rules!(
+        "DEFAULT" | "ID" = pattern r"[a-z]+";
+        "DEFAULT" | "IF" = string "if";
+    );
+    let lexemes = santiago::lexer::lex(&lexer_rules, "if").unwrap();
+    assert_eq!(lexemes[0].kind, "ID");
+}
+
 fn run<AST>(
     name: &str,
     lexer_rules: &santiago::lexer::LexerRules,
@@ -118,6 +2003,10 @@ fn run<AST>(
             .to_string();
 
         let lexemes = santiago::lexer::lex(lexer_rules, &input).unwrap();
+        assert_eq!(
+            santiago::lexer::lex(&lexer_rules.compile(), &input).unwrap(),
+            lexemes
+        );
         let lexemes_str: String = lexemes
             .iter()
             .map(|lexeme| lexeme.to_string())
@@ -148,6 +2037,30 @@ fn run<AST>(
         }
 
         let parse_trees = santiago::parser::parse(grammar, &lexemes).unwrap();
+        for parse_tree in &parse_trees {
+            let cst = parse_tree.cst();
+            let text: String = cst
+                .lexemes()
+                .into_iter()
+                .map(|lexeme| {
+                    let mut text = String::new();
+                    for trivia in &lexeme.leading_trivia {
+                        text.push_str(&trivia.raw);
+                    }
+                    text.push_str(&input[lexeme.byte_range()]);
+                    for trivia in &lexeme.trailing_trivia {
+                        text.push_str(&trivia.raw);
+                    }
+                    text
+                })
+                .collect();
+            assert_eq!(text, input);
+
+            // The Nix lexer unescapes the contents of the strings.
+            if name != "nix" {
+                assert_eq!(cst.text(), input);
+            }
+        }
         let parse_trees_str: String = parse_trees
             .iter()
             .map(|parse_tree| format!("---\n{parse_tree}"))
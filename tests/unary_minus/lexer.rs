@@ -0,0 +1 @@
+include!("../calculator/lexer.rs");